@@ -0,0 +1,33 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use sos::{interpreter, parser, vm};
+
+use std::io::{self, Cursor};
+
+// Recurses once for every line of input, so the depth is the number of lines.
+const PROGRAM: &str = "ö ¤ given that @ >> {ö) otherwise /done
+ö";
+
+type Backend =
+    fn(&mut interpreter::State, &[parser::Expr]) -> interpreter::Result<interpreter::Value>;
+
+fn recursion(c: &mut Criterion) {
+    let ast = parser::parse(&parser::State::default(), PROGRAM).expect("Parsing failed");
+    let mut group = c.benchmark_group("recursion");
+    let backends: [(&str, Backend); 2] = [("interpreter", interpreter::interpret), ("vm", vm::run)];
+    for &(name, backend) in &backends {
+        for &depth in &[100, 200, 400, 800] {
+            let input = "Ok\n".repeat(depth);
+            group.bench_with_input(BenchmarkId::new(name, depth), &input, |b, input| {
+                b.iter(|| {
+                    let mut state =
+                        interpreter::State::with_io(Cursor::new(input.clone()), io::sink());
+                    backend(&mut state, &ast).expect("Evaluation failed")
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, recursion);
+criterion_main!(benches);
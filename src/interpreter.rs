@@ -1,64 +1,377 @@
 use crate::parser::VectorComponent;
-use crate::parser::{Expr, Ident, Op};
+use crate::parser::{Expr, Ident, Op, ReadMode};
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
 use rand::prelude::*;
 use rand::rngs::SmallRng;
 use unicode_reverse::reverse_grapheme_clusters_in_place;
 
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::iter::once;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::iter::successors;
+use std::rc::Rc;
+
+/// Functions defined in one scope or call, linked to the enclosing ones.
+struct Functions {
+    names: RefCell<HashMap<Ident, Rc<Expr>>>,
+    parent: Option<Rc<Functions>>,
+}
+
+// Frames are unlinked one by one because dropping long chains left by deep recursion recursively
+// would overflow the stack.
+impl Drop for Functions {
+    fn drop(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(mut frame) = parent.and_then(|f| Rc::try_unwrap(f).ok()) {
+            parent = frame.parent.take();
+        }
+    }
+}
+
+/// Functions and params visible at some point of the program.
+///
+/// Frames are shared with the enclosing scopes and calls, so entering one doesn't copy anything.
+#[derive(Clone, Default)]
+pub struct Env {
+    functions: Option<Rc<Functions>>,
+    // Arguments of the innermost call, which are the only params visible.
+    params: Option<Rc<Vec<Value>>>,
+}
+
+impl Env {
+    fn with_functions(&self) -> Self {
+        Env {
+            functions: Some(Rc::new(Functions {
+                names: RefCell::new(HashMap::new()),
+                parent: self.functions.clone(),
+            })),
+            params: self.params.clone(),
+        }
+    }
+
+    /// Arguments of the innermost call, if there is one.
+    pub(crate) fn params(&self) -> Option<&[Value]> {
+        self.params.as_deref().map(Vec::as_slice)
+    }
+
+    pub(crate) fn with_params(&self, values: Vec<Value>) -> Self {
+        Env {
+            functions: self.functions.clone(),
+            params: Some(Rc::new(values)),
+        }
+    }
+}
 
 pub struct State {
-    functions: Vec<HashMap<Ident, Expr>>,
-    params: Vec<Vec<Value>>,
-    rng: SmallRng,
+    env: Env,
+    // Whether the innermost function frame of `env` belongs to this scope or call. Frames are only
+    // created once something is defined, which keeps lookups from walking empty ones.
+    local: bool,
+    rng: Rc<RefCell<SmallRng>>,
+    overflow: Overflow,
+    limits: Limits,
+    // Steps taken by the whole evaluation since the limits were set.
+    steps: Rc<Cell<u64>>,
+    // Number of calls being evaluated, not counting the ones in tail position.
+    depth: usize,
+    input: Rc<RefCell<dyn BufRead>>,
+    output: Rc<RefCell<dyn Write>>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl State {
     pub fn new() -> Self {
+        Self::with_output(io::stdout())
+    }
+
+    /// Creates state whose `@ <<` writes go to `output` instead of stdout.
+    pub fn with_output(output: impl Write + 'static) -> Self {
+        Self::with_io(BufReader::new(io::stdin()), output)
+    }
+
+    /// Creates state whose randomness is fully determined by `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut state = Self::new();
+        state.seed(seed);
+        state
+    }
+
+    /// Creates state whose `@ >>` reads come from `input` and `@ <<` writes go to `output`.
+    pub fn with_io(input: impl BufRead + 'static, output: impl Write + 'static) -> Self {
         Self {
-            rng: SmallRng::from_entropy(),
-            functions: vec![HashMap::new()],
-            params: vec![],
+            rng: Rc::new(RefCell::new(SmallRng::from_entropy())),
+            overflow: Overflow::Checked,
+            limits: Limits::default(),
+            steps: Rc::new(Cell::new(0)),
+            depth: 0,
+            env: Env::default().with_functions(),
+            local: true,
+            input: Rc::new(RefCell::new(input)),
+            output: Rc::new(RefCell::new(output)),
         }
     }
 
-    fn within_scope<T>(&self, mut f: impl FnMut(State) -> T) -> T {
-        f(Self {
-            rng: SmallRng::from_entropy(),
-            functions: self
-                .functions
-                .iter()
-                .cloned()
-                .chain(once(HashMap::new()))
-                .collect(),
-            params: self.params.clone(),
-        })
+    /// Creates state seeing `env` whose own definitions stay local to it.
+    pub(crate) fn enter(&self, env: Env) -> State {
+        Self {
+            env,
+            local: false,
+            rng: self.rng.clone(),
+            overflow: self.overflow,
+            limits: self.limits,
+            steps: self.steps.clone(),
+            depth: self.depth,
+            input: self.input.clone(),
+            output: self.output.clone(),
+        }
     }
 
-    fn with_params<T>(&self, params: Vec<Value>, mut f: impl FnMut(State) -> T) -> T {
-        f(Self {
-            rng: SmallRng::from_entropy(),
-            functions: self.functions.clone(),
-            params: self.params.iter().cloned().chain(once(params)).collect(),
-        })
+    pub(crate) fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Restarts the random number generator shared by the whole evaluation from `seed`.
+    pub fn seed(&mut self, seed: u64) {
+        *self.rng.borrow_mut() = SmallRng::seed_from_u64(seed);
     }
 
-    fn add(&mut self, name: Ident, body: Expr) {
-        self.functions.last_mut().unwrap().insert(name, body);
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
     }
 
-    fn resolve_fun(&self, name: &Ident) -> Option<&Expr> {
-        self.functions.iter().rev().flat_map(|m| m.get(name)).next()
+    /// Limits the resources the evaluation may use from now on.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.steps.set(0);
     }
 
-    fn resolve_param(&self, mut param: u64) -> Option<&Value> {
-        for cur in &self.params {
-            if param < cur.len() as u64 {
-                return Some(&cur[param as usize]);
+    pub(crate) fn step(&self, count: u64) -> Result<()> {
+        let steps = self.steps.get() + count;
+        self.steps.set(steps);
+        match self.limits.steps {
+            Some(max) if steps > max => Err(ErrorKind::LimitExceeded(Limit::Steps(max)).into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Makes this the state of a call nested `depth` calls deep.
+    pub(crate) fn set_depth(&mut self, depth: usize) -> Result<()> {
+        match self.limits.call_depth {
+            Some(max) if depth > max => Err(ErrorKind::LimitExceeded(Limit::CallDepth(max)).into()),
+            _ => {
+                self.depth = depth;
+                Ok(())
             }
-            param -= cur.len() as u64;
         }
-        None
+    }
+
+    pub(crate) fn check_size(&self, value: &Value) -> Result<()> {
+        match value {
+            Value::Vector(v) => {
+                self.check_vector_length(v.len())?;
+                self.check_vector_bits(v)
+            }
+            Value::Text(t) => self.check_text_size(t.len()),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_vector_length(&self, len: usize) -> Result<()> {
+        match self.limits.vector_length {
+            Some(max) if len > max => {
+                Err(ErrorKind::LimitExceeded(Limit::VectorLength(max)).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Repeated multiplication grows components exponentially even when the vector stays short.
+    fn check_vector_bits(&self, v: &[BigInt]) -> Result<()> {
+        match self.limits.vector_bits {
+            Some(max) if v.iter().map(BigInt::bits).sum::<u64>() > max => {
+                Err(ErrorKind::LimitExceeded(Limit::VectorBits(max)).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_text_size(&self, len: usize) -> Result<()> {
+        match self.limits.text_size {
+            Some(max) if len > max => Err(ErrorKind::LimitExceeded(Limit::TextSize(max)).into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn wrap(&self, n: BigInt) -> BigInt {
+        match self.overflow {
+            Overflow::Checked => n,
+            Overflow::Wrapping => {
+                BigInt::from((n & BigInt::from(u64::MAX)).to_u64().unwrap() as i64)
+            }
+        }
+    }
+
+    fn sum(&self, v: &[BigInt]) -> BigInt {
+        self.wrap(v.iter().sum())
+    }
+
+    pub(crate) fn add(&mut self, name: Ident, body: Rc<Expr>) {
+        if !self.local {
+            self.env = self.env.with_functions();
+            self.local = true;
+        }
+        let frame = self.env.functions.as_ref().unwrap();
+        frame.names.borrow_mut().insert(name, body);
+    }
+
+    /// Forgets every function defined so far, keeping input, output and the other settings.
+    pub fn clear_definitions(&mut self) {
+        self.env = Env::default().with_functions();
+        self.local = true;
+    }
+
+    fn function_frames(&self) -> impl Iterator<Item = &Rc<Functions>> {
+        successors(self.env.functions.as_ref(), |f| f.parent.as_ref())
+    }
+
+    /// Functions defined at the top level.
+    pub fn definitions(&self) -> Vec<(Ident, Rc<Expr>)> {
+        self.function_frames()
+            .last()
+            .map(|frame| {
+                let names = frame.names.borrow();
+                names
+                    .iter()
+                    .map(|(name, body)| (name.clone(), body.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Finds the function `name` refers to along with the env it was defined in.
+    ///
+    /// Calls bring their own params, so that env is just the frame holding the definition, which
+    /// keeps frames from owning themselves.
+    pub(crate) fn resolve_fun(&self, name: &Ident) -> Option<Closure> {
+        self.function_frames()
+            .flat_map(|frame| {
+                let names = frame.names.borrow();
+                names.get(name).map(|body| Closure {
+                    body: body.clone(),
+                    env: Env {
+                        functions: Some(frame.clone()),
+                        params: None,
+                    },
+                })
+            })
+            .next()
+    }
+
+    /// Reads line of input without its line ending, or `None` at the end of input.
+    pub fn read_line(&self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.borrow_mut().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    pub(crate) fn read_all(&self) -> io::Result<Option<String>> {
+        let mut all = String::new();
+        self.input.borrow_mut().read_to_string(&mut all)?;
+        Ok(if all.is_empty() { None } else { Some(all) })
+    }
+
+    pub(crate) fn write(&self, value: &Value) -> io::Result<()> {
+        writeln!(self.output.borrow_mut(), "{}", value)
+    }
+}
+
+/// How numbers behave when they outgrow machine integers.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Overflow {
+    /// Numbers are unbounded and sizes that can't be represented are runtime errors.
+    Checked,
+    /// Every arithmetic result wraps around like a 64-bit two's complement integer.
+    Wrapping,
+}
+
+/// Resources an evaluation may use, unlimited when `None`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// Evaluated expressions.
+    pub steps: Option<u64>,
+    /// Nested function calls, where calls in tail position replace their caller.
+    pub call_depth: Option<usize>,
+    /// Components of a vector.
+    pub vector_length: Option<usize>,
+    /// Bits of all components of a vector together.
+    pub vector_bits: Option<u64>,
+    /// Bytes of a text.
+    pub text_size: Option<usize>,
+}
+
+/// Limit that was exceeded, with its maximum.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Limit {
+    Steps(u64),
+    CallDepth(usize),
+    VectorLength(usize),
+    VectorBits(u64),
+    TextSize(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Steps(max) => write!(fmt, "{} steps", max),
+            Limit::CallDepth(max) => write!(fmt, "{} nested calls", max),
+            Limit::VectorLength(max) => write!(fmt, "{} vector components", max),
+            Limit::VectorBits(max) => write!(fmt, "{} bits of vector components", max),
+            Limit::TextSize(max) => write!(fmt, "{} bytes of text", max),
+        }
+    }
+}
+
+/// In-memory output whose contents stay readable after being given to `State::with_output`.
+#[derive(Clone, Default)]
+pub struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Buffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
@@ -66,52 +379,250 @@ impl State {
 pub enum Value {
     Text(String),
     Boolean(bool),
-    Vector(Vec<i64>), // TODO: Big integerize?
-    Function(Expr),   // TODO: Anonymous vs non?
+    Vector(Vec<BigInt>),
+    Function(Closure),
     Empty,
 }
 
-pub fn interpret(state: &State, ast: &[Expr]) -> Value {
-    // TODO: What should be the value of top level? Where you can define functions? Etc.
-    for expr in ast {}
-    Value::Empty
+/// Function body together with the environment it was defined in.
+#[derive(Clone)]
+pub struct Closure {
+    pub body: Rc<Expr>,
+    pub(crate) env: Env,
+}
+
+impl Closure {
+    /// Creates function that sees nothing but its own params.
+    pub fn new(body: Expr) -> Self {
+        Closure {
+            body: Rc::new(body),
+            env: Env::default(),
+        }
+    }
+}
+
+// Functions are equal if their bodies are, regardless of the names of the functions defined in
+// them.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        self.body.alpha_eq(&other.body)
+    }
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("Closure").field(&self.body).finish()
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Text(t) => write!(fmt, "{}", t),
+            Value::Boolean(b) => write!(fmt, "{}", b),
+            Value::Vector(v) => {
+                let mut components = v.iter();
+                if let Some(first) = components.next() {
+                    write!(fmt, "{}", first)?;
+                }
+                for c in components {
+                    write!(fmt, " {}", c)?;
+                }
+                Ok(())
+            }
+            Value::Function(_) => write!(fmt, "¤"),
+            Value::Empty => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    UndefinedFunction(Ident),
+    UnboundParam(u64),
+    InvalidNumber(String),
+    NotAFunction(Value),
+    Overflow(&'static str),
+    LimitExceeded(Limit),
+    Io(io::Error),
+}
+
+/// Error that stopped the evaluation together with the innermost expression being evaluated.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub expr: Option<Box<Expr>>,
+}
+
+impl RuntimeError {
+    pub(crate) fn at(mut self, expr: &Expr) -> Self {
+        if self.expr.is_none() {
+            self.expr = Some(Box::new(expr.clone()));
+        }
+        self
+    }
+}
+
+impl From<ErrorKind> for RuntimeError {
+    fn from(kind: ErrorKind) -> Self {
+        RuntimeError { kind, expr: None }
+    }
+}
+
+impl From<io::Error> for RuntimeError {
+    fn from(e: io::Error) -> Self {
+        ErrorKind::Io(e).into()
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use self::ErrorKind::*;
+        match &self.kind {
+            UndefinedFunction(name) => {
+                write!(fmt, "Function with name `{}` wasn't defined", name.0)
+            }
+            UnboundParam(param) => write!(fmt, "Unbound param `{}`", param),
+            InvalidNumber(n) => write!(fmt, "Couldn't read `{}` as a number", n),
+            NotAFunction(v) => write!(fmt, "Can't apply `{}` which isn't a function", v),
+            Overflow(op) => write!(fmt, "Overflow in {}", op),
+            LimitExceeded(limit) => write!(fmt, "Exceeded the limit of {}", limit),
+            Io(e) => write!(fmt, "IO failed: {}", e),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+pub type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// Evaluates program in order and returns the value of the last expression.
+pub fn interpret(state: &mut State, ast: &[Expr]) -> Result<Value> {
+    ast.iter()
+        .try_fold(Value::Empty, |_, expr| interpret_expr(state, expr))
+}
+
+/// Expression in tail position, whose value is the value of the expression being evaluated.
+enum Tail<'a> {
+    Done(Value),
+    Expr(&'a Expr),
+    /// Expression evaluated in a scope of its own.
+    Within(State, &'a Expr),
+    /// Function body evaluated in the state of the call.
+    Call(State, Rc<Expr>),
+}
+
+/// Evaluates expression. Expressions in tail position replace the one being evaluated instead of
+/// being evaluated recursively, so loops written as recursion don't grow the stack.
+pub fn interpret_expr(state: &mut State, expr: &Expr) -> Result<Value> {
+    interpret_tail(state, Tail::Expr(expr))
 }
 
-pub fn interpret_expr(state: &mut State, expr: &Expr) -> Value {
-    use self::Expr::*;
-    match expr {
-        Scope(e) => state.within_scope(|mut state| interpret_expr(&mut state, &*e)),
-        Op(lhs, op, rhs) => interpret_op(state, &*lhs, op, &*rhs),
+fn interpret_tail(state: &mut State, tail: Tail) -> Result<Value> {
+    let depth = state.depth + 1;
+    let mut inner;
+    let mut body;
+    let mut state = state;
+    let mut tail = tail;
+    loop {
+        let expr = match tail {
+            Tail::Done(value) => return Ok(value),
+            Tail::Expr(next) => next,
+            Tail::Within(next_state, next) => {
+                inner = next_state;
+                state = &mut inner;
+                next
+            }
+            Tail::Call(mut next_state, next) => {
+                next_state.set_depth(depth).map_err(|e| e.at(&next))?;
+                inner = next_state;
+                body = next;
+                state = &mut inner;
+                &*body
+            }
+        };
+        tail = interpret_step(state, expr).map_err(|e| e.at(expr))?;
+    }
+}
+
+fn interpret_step<'a>(state: &mut State, expr: &'a Expr) -> Result<Tail<'a>> {
+    use crate::parser::ExprKind::*;
+    state.step(1)?;
+    let value = match &expr.kind {
+        Scope(e) => return Ok(Tail::Within(state.enter(state.env.clone()), e)),
+        Op(lhs, op, rhs) => interpret_op(state, lhs, op, rhs),
         Conditional {
             condition,
             success,
             failure,
-        } => interpret_conditional(state, condition, success, failure),
-        Definition(name, body) => interpret_definition(state, name, body),
-        Call(name, params) => interpret_call(state, name, params),
+            ..
+        } => return interpret_conditional(state, condition, success, failure),
+        Definition(name, body) => Ok(interpret_definition(state, name, body)),
+        Call(name, params) => return interpret_call(state, name, params),
+        Lambda(body) => Ok(interpret_lambda(state, body)),
+        Apply(callee, params) => return interpret_apply(state, callee, params),
         Param(param) => interpret_param(state, param.0),
-        Text(text) => Value::Text(text.to_owned()),
+        Text(text) => Ok(Value::Text(text.to_owned())),
         Vector(components) => interpret_vector(state, components),
-        WriteIO(src) => Value::Empty,
-        ReadIO => Value::Empty,
-    }
+        WriteIO(src) => interpret_write(state, src),
+        ReadIO(mode) => interpret_read(state, mode),
+    }?;
+    state.check_size(&value)?;
+    Ok(Tail::Done(value))
 }
 
-pub fn interpret_op(state: &mut State, lhs: &Expr, op: &Op, rhs: &Expr) -> Value {
+pub fn interpret_write(state: &mut State, src: &Expr) -> Result<Value> {
+    let value = interpret_expr(state, src)?;
+    state.write(&value)?;
+    Ok(Value::Empty)
+}
+
+pub fn interpret_read(state: &mut State, mode: &ReadMode) -> Result<Value> {
+    let input = match mode {
+        ReadMode::Line | ReadMode::Numbers => state.read_line(),
+        ReadMode::All => state.read_all(),
+    }?;
+    Ok(match (mode, input) {
+        (_, None) => Value::Empty,
+        (ReadMode::Numbers, Some(line)) => Value::Vector(
+            line.split_whitespace()
+                .map(|n| {
+                    n.parse()
+                        .map(|n| state.wrap(n))
+                        .map_err(|_| ErrorKind::InvalidNumber(n.into()).into())
+                })
+                .collect::<Result<_>>()?,
+        ),
+        (_, Some(text)) => Value::Text(text),
+    })
+}
+
+pub fn interpret_op(state: &mut State, lhs: &Expr, op: &Op, rhs: &Expr) -> Result<Value> {
     use self::Op::*;
-    let lhs = interpret_expr(state, &*lhs);
-    let rhs = interpret_expr(state, &*rhs);
+    let lhs = interpret_expr(state, lhs)?;
+    let rhs = interpret_expr(state, rhs)?;
     match op {
         Add => interpret_addition(state, lhs, rhs),
-        Equ => Value::Boolean(lhs == rhs),
-        Mul => lhs,
-        Sub => lhs,
+        Equ => Ok(Value::Boolean(lhs == rhs)),
+        Mul => interpret_multiplication(state, lhs, rhs),
+        Sub => interpret_subtraction(state, lhs, rhs),
     }
 }
 
-pub fn interpret_addition(state: &mut State, lhs: Value, rhs: Value) -> Value {
+/// Adds two values:
+///
+/// - boolean + boolean: exclusive or.
+/// - boolean + text: `true` reverses grapheme clusters of the text, `false` keeps it.
+/// - boolean + vector: `true` shuffles components of the vector, `false` keeps it.
+/// - text + boolean, vector + boolean: same as `!boolean + value`.
+/// - text + text: texts joined with a space.
+/// - text + vector, vector + text: digits of the components concatenated with the text.
+/// - vector + vector: component-wise sum with trailing zeros trimmed.
+/// - function + value, value + function: the function is evaluated first.
+/// - empty + value, value + empty: the value.
+pub fn interpret_addition(state: &mut State, lhs: Value, rhs: Value) -> Result<Value> {
     use self::Value::*;
-    match (lhs, rhs) {
+    Ok(match (lhs, rhs) {
         (Boolean(lhs), Boolean(rhs)) => Boolean(lhs ^ rhs),
         (Boolean(lhs), Text(mut rhs)) => {
             if lhs {
@@ -121,10 +632,12 @@ pub fn interpret_addition(state: &mut State, lhs: Value, rhs: Value) -> Value {
                 Text(rhs)
             }
         }
-        (lhs @ Text(_), Boolean(rhs)) => interpret_addition(state, Boolean(!rhs), lhs),
+        (lhs @ Text(_), Boolean(rhs)) | (lhs @ Vector(_), Boolean(rhs)) => {
+            return interpret_addition(state, Boolean(!rhs), lhs)
+        }
         (Boolean(lhs), Vector(mut rhs)) => {
             if lhs {
-                rhs.shuffle(&mut state.rng);
+                rhs.shuffle(&mut *state.rng.borrow_mut());
                 Vector(rhs)
             } else {
                 Vector(rhs)
@@ -134,119 +647,318 @@ pub fn interpret_addition(state: &mut State, lhs: Value, rhs: Value) -> Value {
         (Text(lhs), Vector(rhs)) => Text(format!(
             "{}{}",
             lhs,
-            rhs.iter().map(|i| i.to_string()).collect::<String>(),
+            rhs.iter().map(BigInt::to_string).collect::<String>(),
         )),
         (Vector(lhs), Text(rhs)) => Text(format!(
             "{}{}",
-            lhs.iter().map(|i| i.to_string()).collect::<String>(),
+            lhs.iter().map(BigInt::to_string).collect::<String>(),
             rhs,
         )),
+        (Vector(lhs), Vector(rhs)) => Vector(trim_zeros(component_wise(lhs, rhs, |l, r| {
+            state.wrap(l + r)
+        }))),
+        (Function(lhs), rhs) => {
+            let lhs = interpret_closure(state, &lhs)?;
+            return interpret_addition(state, lhs, rhs);
+        }
+        (lhs, Function(rhs)) => {
+            let rhs = interpret_closure(state, &rhs)?;
+            return interpret_addition(state, lhs, rhs);
+        }
+        (v, Empty) | (Empty, v) => v,
+    })
+}
+
+/// Multiplies two values:
+///
+/// - boolean * boolean: and.
+/// - boolean * text, boolean * vector: `true` keeps the value, `false` empties it.
+/// - text * boolean, vector * boolean: same as `boolean * value`.
+/// - text * text: characters of the left text that appear in the right one.
+/// - text * vector, vector * text: text repeated by the sum of components, reversed if negative;
+///   fails with an overflow if the repeated text can't be represented.
+/// - vector * vector: scaling if either has one component, otherwise dot product.
+/// - function * value, value * function: the function is evaluated first.
+/// - empty * value, value * empty: empty.
+pub fn interpret_multiplication(state: &mut State, lhs: Value, rhs: Value) -> Result<Value> {
+    use self::Value::*;
+    Ok(match (lhs, rhs) {
+        (Boolean(lhs), Boolean(rhs)) => Boolean(lhs && rhs),
+        (Boolean(lhs), Text(rhs)) => Text(if lhs { rhs } else { String::new() }),
+        (Boolean(lhs), Vector(rhs)) => Vector(if lhs { rhs } else { vec![] }),
+        (lhs @ Text(_), rhs @ Boolean(_)) | (lhs @ Vector(_), rhs @ Boolean(_)) => {
+            return interpret_multiplication(state, rhs, lhs)
+        }
+        (Text(lhs), Text(rhs)) => Text(lhs.chars().filter(|c| rhs.contains(*c)).collect()),
+        (Text(mut lhs), Vector(rhs)) => {
+            let times = state.sum(&rhs);
+            if times.is_negative() {
+                reverse_grapheme_clusters_in_place(&mut lhs);
+            }
+            let times = times
+                .abs()
+                .to_usize()
+                .filter(|&n| {
+                    lhs.len()
+                        .checked_mul(n)
+                        .is_some_and(|len| len <= isize::MAX as usize)
+                })
+                .ok_or(ErrorKind::Overflow("text repetition"))?;
+            state.check_text_size(lhs.len() * times)?;
+            Text(lhs.repeat(times))
+        }
+        (lhs @ Vector(_), rhs @ Text(_)) => return interpret_multiplication(state, rhs, lhs),
         (Vector(lhs), Vector(rhs)) => {
-            let mut res = vec![];
-            for i in 0..lhs.len().max(rhs.len()) {
-                res.push(lhs.get(i).unwrap_or(&0) + lhs.get(i).unwrap_or(&0))
-            }
-            while let Some(v) = res.pop() {
-                if v != 0 {
-                    res.push(v);
-                    break;
-                }
+            let res = match (&lhs[..], &rhs[..]) {
+                ([scalar], v) | (v, [scalar]) => v.iter().map(|c| state.wrap(c * scalar)).collect(),
+                (lhs, rhs) => vec![state.wrap(lhs.iter().zip(rhs).map(|(l, r)| l * r).sum())],
+            };
+            Vector(trim_zeros(res))
+        }
+        (Function(lhs), rhs) => {
+            let lhs = interpret_closure(state, &lhs)?;
+            return interpret_multiplication(state, lhs, rhs);
+        }
+        (lhs, Function(rhs)) => {
+            let rhs = interpret_closure(state, &rhs)?;
+            return interpret_multiplication(state, lhs, rhs);
+        }
+        (_, Empty) | (Empty, _) => Empty,
+    })
+}
+
+/// Subtracts two values:
+///
+/// - boolean - boolean: exclusive or.
+/// - boolean - text: `true` sorts characters of the text, `false` keeps it.
+/// - boolean - vector: `true` sorts components of the vector, `false` keeps it.
+/// - text - boolean, vector - boolean: same as `!boolean - value`.
+/// - text - text: occurrences of the right text removed from the left one.
+/// - text - vector: as many characters as the sum of components removed from the end, or from
+///   the start if negative.
+/// - vector - text: as many characters as the sum of components removed from the start.
+/// - vector - vector: component-wise difference with trailing zeros trimmed.
+/// - function - value, value - function: the function is evaluated first.
+/// - value - empty: the value.
+/// - empty - vector: negated vector, empty - boolean: the boolean, empty - text: empty.
+pub fn interpret_subtraction(state: &mut State, lhs: Value, rhs: Value) -> Result<Value> {
+    use self::Value::*;
+    Ok(match (lhs, rhs) {
+        (Boolean(lhs), Boolean(rhs)) => Boolean(lhs ^ rhs),
+        (Boolean(lhs), Text(rhs)) => {
+            if lhs {
+                let mut chars = rhs.chars().collect::<Vec<_>>();
+                chars.sort_unstable();
+                Text(chars.into_iter().collect())
+            } else {
+                Text(rhs)
             }
-            Vector(res)
         }
+        (Boolean(lhs), Vector(mut rhs)) => {
+            if lhs {
+                rhs.sort_unstable();
+            }
+            Vector(rhs)
+        }
+        (lhs @ Text(_), Boolean(rhs)) | (lhs @ Vector(_), Boolean(rhs)) => {
+            return interpret_subtraction(state, Boolean(!rhs), lhs)
+        }
+        (Text(lhs), Text(rhs)) => Text(if rhs.is_empty() {
+            lhs
+        } else {
+            lhs.replace(&rhs, "")
+        }),
+        (Text(lhs), Vector(rhs)) => {
+            let n = state.sum(&rhs);
+            let count = lhs.chars().count();
+            let removed = to_count(&n.abs()).min(count);
+            Text(if n.is_negative() {
+                lhs.chars().skip(removed).collect()
+            } else {
+                lhs.chars().take(count - removed).collect()
+            })
+        }
+        (Vector(lhs), Text(rhs)) => {
+            let n = state.sum(&lhs);
+            Text(rhs.chars().skip(to_count(&n)).collect())
+        }
+        (Vector(lhs), Vector(rhs)) => Vector(trim_zeros(component_wise(lhs, rhs, |l, r| {
+            state.wrap(l - r)
+        }))),
         (Function(lhs), rhs) => {
-            let lhs = interpret_expr(state, &lhs);
-            interpret_addition(state, lhs, rhs)
+            let lhs = interpret_closure(state, &lhs)?;
+            return interpret_subtraction(state, lhs, rhs);
         }
         (lhs, Function(rhs)) => {
-            let rhs = interpret_expr(state, &rhs);
-            interpret_addition(state, lhs, rhs)
+            let rhs = interpret_closure(state, &rhs)?;
+            return interpret_subtraction(state, lhs, rhs);
         }
         (v, Empty) => v,
-        (Empty, v) => v,
-        // TOOO: Other cases
-        _ => unimplemented!(),
+        (Empty, Vector(rhs)) => Vector(rhs.into_iter().map(|c| state.wrap(-c)).collect()),
+        (Empty, b @ Boolean(_)) => b,
+        (Empty, Text(_)) => Empty,
+    })
+}
+
+fn component_wise(
+    lhs: Vec<BigInt>,
+    rhs: Vec<BigInt>,
+    f: impl Fn(&BigInt, &BigInt) -> BigInt,
+) -> Vec<BigInt> {
+    let zero = BigInt::zero();
+    (0..lhs.len().max(rhs.len()))
+        .map(|i| f(lhs.get(i).unwrap_or(&zero), rhs.get(i).unwrap_or(&zero)))
+        .collect()
+}
+
+fn trim_zeros(mut vector: Vec<BigInt>) -> Vec<BigInt> {
+    while vector.last().is_some_and(Zero::is_zero) {
+        vector.pop();
     }
+    vector
 }
 
-pub fn interpret_conditional(
+// Counts of removed characters beyond `usize` remove everything anyway, so they saturate.
+fn to_count(n: &BigInt) -> usize {
+    if n.is_negative() {
+        0
+    } else {
+        n.to_usize().unwrap_or(usize::MAX)
+    }
+}
+
+fn interpret_conditional<'a>(
     state: &mut State,
     condition: &Expr,
-    success: &Expr,
-    failure: &Option<Expr>,
-) -> Value {
-    let condition = interpret_expr(state, condition);
-    if is_truthy(state, condition) {
-        interpret_expr(state, success)
+    success: &'a Expr,
+    failure: &'a Option<Expr>,
+) -> Result<Tail<'a>> {
+    let condition = interpret_expr(state, condition)?;
+    Ok(if is_truthy(state, condition)? {
+        Tail::Expr(success)
     } else {
         failure
             .as_ref()
-            .map(|f| interpret_expr(state, f))
-            .unwrap_or_else(|| Value::Empty)
-    }
+            .map_or(Tail::Done(Value::Empty), Tail::Expr)
+    })
 }
 
-pub fn is_truthy(state: &mut State, value: Value) -> bool {
-    match value {
+pub fn is_truthy(state: &mut State, value: Value) -> Result<bool> {
+    Ok(match value {
         Value::Boolean(b) => b,
         Value::Vector(c) => {
-            c.iter()
-                .enumerate()
-                .fold(3i64, |acc, (i, cur)| acc ^ ((cur << (acc & 7)) * i as i64))
-                % 2
+            // Only the lowest three bits of the accumulator ever matter, and they depend only on
+            // the lowest three bits of each component.
+            let low_bits = |n: &BigInt| (n & BigInt::from(7)).to_i64().unwrap();
+            c.iter().enumerate().fold(3i64, |acc, (i, cur)| {
+                (acc ^ ((low_bits(cur) << (acc & 7)) * (i & 7) as i64)) & 7
+            }) % 2
                 == 0
         }
         Value::Text(t) => t.chars().all(|c| c == 'O' || c == 'k'),
-        Value::Function(e) => {
-            let res = interpret_expr(state, &e);
-            is_truthy(state, res)
+        Value::Function(f) => {
+            let res = interpret_closure(state, &f)?;
+            return is_truthy(state, res);
         }
         _ => false,
-    }
+    })
 }
 
 pub fn interpret_definition(state: &mut State, name: &Ident, body: &Expr) -> Value {
+    let body = Rc::new(body.clone());
     state.add(name.clone(), body.clone());
-    Value::Function(body.clone())
+    Value::Function(Closure {
+        body,
+        env: state.env.clone(),
+    })
 }
 
-pub fn interpret_call(state: &mut State, name: &Ident, params: &[Expr]) -> Value {
-    let params = params.iter().map(|p| interpret_expr(state, p)).collect();
+fn interpret_call<'a>(state: &mut State, name: &Ident, params: &[Expr]) -> Result<Tail<'a>> {
+    let params = params
+        .iter()
+        .map(|p| interpret_expr(state, p))
+        .collect::<Result<_>>()?;
     let fun = state
         .resolve_fun(name)
-        .unwrap_or_else(|| panic!("Function with name `{}` wasn't defined.", name.0));
-    state.with_params(params, |mut state| interpret_expr(&mut state, fun))
+        .ok_or_else(|| ErrorKind::UndefinedFunction(name.clone()))?;
+    Ok(Tail::Call(
+        state.enter(fun.env.with_params(params)),
+        fun.body,
+    ))
 }
 
-pub fn interpret_param(state: &State, param: u64) -> Value {
-    state
-        .resolve_param(param)
-        .unwrap_or_else(|| panic!("Unbound param `{}`", param))
+pub fn interpret_lambda(state: &State, body: &Expr) -> Value {
+    Value::Function(Closure {
+        body: Rc::new(body.clone()),
+        env: state.env.clone(),
+    })
+}
+
+fn interpret_apply<'a>(state: &mut State, callee: &Expr, params: &[Expr]) -> Result<Tail<'a>> {
+    let closure = match interpret_expr(state, callee)? {
+        Value::Function(closure) => closure,
+        v => return Err(ErrorKind::NotAFunction(v).into()),
+    };
+    let params = params
+        .iter()
+        .map(|p| interpret_expr(state, p))
+        .collect::<Result<_>>()?;
+    let env = closure.env.with_params(params);
+    Ok(Tail::Call(state.enter(env), closure.body))
+}
+
+/// Evaluates function value in the environment it was defined in.
+pub fn interpret_closure(state: &mut State, closure: &Closure) -> Result<Value> {
+    let call = Tail::Call(state.enter(closure.env.clone()), closure.body.clone());
+    interpret_tail(state, call)
+}
+
+/// Evaluates param of the innermost call: `\\` is a vector of all its arguments and the rest are
+/// numbered from one.
+pub fn interpret_param(state: &mut State, param: u64) -> Result<Value> {
+    let params = state
+        .env
+        .params
         .clone()
+        .ok_or(ErrorKind::UnboundParam(param))?;
+    if param == 0 {
+        let mut res = vec![];
+        for value in params.iter() {
+            res.extend(vectorize(state, value.clone())?);
+        }
+        return Ok(Value::Vector(res));
+    }
+    (param - 1)
+        .to_usize()
+        .and_then(|i| params.get(i))
+        .cloned()
+        .ok_or_else(|| ErrorKind::UnboundParam(param).into())
 }
 
-pub fn interpret_vector(state: &mut State, parts: &[VectorComponent]) -> Value {
+pub fn interpret_vector(state: &mut State, parts: &[VectorComponent]) -> Result<Value> {
     use self::VectorComponent::*;
-    Value::Vector(
-        parts
-            .iter()
-            .flat_map(|component| match component {
-                Number(n) => vec![*n as i64],
-                Param(p) => vectorize(state, interpret_param(state, p.0)),
-            })
-            .collect(),
-    )
+    let mut res = vec![];
+    for component in parts {
+        match component {
+            Number(n, _) => res.push(state.wrap(BigInt::from(*n))),
+            Param(p) => {
+                let value = interpret_param(state, p.0)?;
+                res.extend(vectorize(state, value)?);
+            }
+        }
+    }
+    Ok(Value::Vector(res))
 }
 
-pub fn vectorize(state: &mut State, value: Value) -> Vec<i64> {
-    match value {
+pub fn vectorize(state: &mut State, value: Value) -> Result<Vec<BigInt>> {
+    Ok(match value {
         Value::Vector(n) => n,
-        Value::Boolean(b) => vec![if b { 42 } else { 7 }],
-        Value::Empty => vec![0],
-        Value::Text(t) => vec![t.len() as i64],
-        Value::Function(e) => {
-            let res = interpret_expr(state, &e);
-            vectorize(state, res)
+        Value::Boolean(b) => vec![BigInt::from(if b { 42 } else { 7 })],
+        Value::Empty => vec![BigInt::zero()],
+        Value::Text(t) => vec![BigInt::from(t.len())],
+        Value::Function(f) => {
+            let res = interpret_closure(state, &f)?;
+            return vectorize(state, res);
         }
-    }
+    })
 }
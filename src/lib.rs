@@ -1,2 +1,4 @@
 pub mod interpreter;
 pub mod parser;
+pub mod printer;
+pub mod vm;
@@ -1,3 +1,198 @@
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use sos::interpreter::{self, Limits};
+use sos::{parser, printer, vm};
+
+mod repl;
+
+use std::fmt::Display;
+use std::fs;
+use std::process;
+use std::str::FromStr;
+use std::thread;
+
+/// Calls may nest this deep unless `--max-depth` is given.
+const DEFAULT_MAX_DEPTH: &str = "10000";
+/// Native stack reserved for each nested call, on top of `BASE_STACK`.
+const STACK_PER_CALL: usize = 16 * 1024;
+/// Native stack reserved for parsing and for evaluating outside of calls.
+const BASE_STACK: usize = 64 * 1024 * 1024;
+
 fn main() {
-    println!("Hello, world!");
+    let matches = App::new("sos")
+        .about("Interpreter for the 🆘 programming language")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Runs a 🆘 program")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Path to the source file")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Seed for random behaviour to make runs reproducible"),
+                )
+                .arg(
+                    Arg::with_name("vm")
+                        .long("vm")
+                        .help("Run compiled bytecode instead of walking the syntax tree"),
+                )
+                .arg(
+                    Arg::with_name("max-steps")
+                        .long("max-steps")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Stop after evaluating this many expressions"),
+                )
+                .arg(
+                    Arg::with_name("max-depth")
+                        .long("max-depth")
+                        .takes_value(true)
+                        .value_name("N")
+                        .default_value(DEFAULT_MAX_DEPTH)
+                        .help("Stop when calls nest deeper than this"),
+                )
+                .arg(
+                    Arg::with_name("max-vector-length")
+                        .long("max-vector-length")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Stop when a vector gets more components than this"),
+                )
+                .arg(
+                    Arg::with_name("max-vector-bits")
+                        .long("max-vector-bits")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Stop when the components of a vector take more bits than this"),
+                )
+                .arg(
+                    Arg::with_name("max-text-size")
+                        .long("max-text-size")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("Stop when a text gets more bytes than this"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Prints a 🆘 program in canonical layout")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Path to the source file")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("write")
+                        .long("write")
+                        .short("w")
+                        .help("Overwrite the file instead of printing it"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("repl").about("Starts an interactive session"))
+        .get_matches();
+
+    match matches.subcommand() {
+        ("run", Some(matches)) => {
+            let path = matches.value_of("FILE").unwrap().to_owned();
+            let seed = value(matches, "seed", "seed");
+            let limits = Limits {
+                steps: value(matches, "max-steps", "step limit"),
+                call_depth: value(matches, "max-depth", "depth limit"),
+                vector_length: value(matches, "max-vector-length", "vector length limit"),
+                vector_bits: value(matches, "max-vector-bits", "vector bits limit"),
+                text_size: value(matches, "max-text-size", "text size limit"),
+            };
+            let use_vm = matches.is_present("vm");
+            // Calls that aren't in tail position recurse on the native stack, so it has to be big
+            // enough to reach the depth limit.
+            let stack = limits
+                .call_depth
+                .and_then(|depth| depth.checked_mul(STACK_PER_CALL))
+                .and_then(|size| size.checked_add(BASE_STACK))
+                .unwrap_or_else(|| {
+                    eprintln!("error: Invalid depth limit: too large");
+                    process::exit(1);
+                });
+            let res = thread::Builder::new()
+                .stack_size(stack)
+                .spawn(move || run(&path, seed, limits, use_vm))
+                .map_err(|e| format!("error: Couldn't reserve stack for the depth limit: {}", e))
+                .and_then(|thread| thread.join().expect("Interpreter panicked"));
+            if let Err(msg) = res {
+                eprintln!("{}", msg);
+                process::exit(1);
+            }
+        }
+        ("fmt", Some(matches)) => {
+            let path = matches.value_of("FILE").unwrap();
+            if let Err(msg) = fmt(path, matches.is_present("write")) {
+                eprintln!("{}", msg);
+                process::exit(1);
+            }
+        }
+        ("repl", _) => {
+            if let Err(e) = repl::repl() {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Parses value of optional argument, exiting if it's invalid.
+fn value<T>(matches: &ArgMatches, name: &str, what: &str) -> Option<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match matches.value_of(name).map(str::parse).transpose() {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("error: Invalid {}: {}", what, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn run(path: &str, seed: Option<u64>, limits: Limits, use_vm: bool) -> Result<(), String> {
+    let code =
+        fs::read_to_string(path).map_err(|e| format!("error: Couldn't read `{}`: {}", path, e))?;
+    let ast = parser::parse(&parser::State::default(), &code).map_err(|e| e.render(path))?;
+
+    let mut state = match seed {
+        Some(seed) => interpreter::State::with_seed(seed),
+        None => interpreter::State::new(),
+    };
+    state.set_limits(limits);
+    let res = if use_vm {
+        vm::run(&mut state, &ast)
+    } else {
+        interpreter::interpret(&mut state, &ast)
+    };
+    res.map(|_| ()).map_err(|e| match &e.expr {
+        Some(expr) => format!(
+            "error: {}\n --> {}:{}:{}",
+            e, path, expr.span.line, expr.span.column
+        ),
+        None => format!("error: {}", e),
+    })
+}
+
+fn fmt(path: &str, write: bool) -> Result<(), String> {
+    let code =
+        fs::read_to_string(path).map_err(|e| format!("error: Couldn't read `{}`: {}", path, e))?;
+    let ast = parser::parse(&parser::State::default(), &code).map_err(|e| e.render(path))?;
+    let formatted = printer::print(&ast);
+    if write {
+        fs::write(path, formatted).map_err(|e| format!("error: Couldn't write `{}`: {}", path, e))
+    } else {
+        print!("{}", formatted);
+        Ok(())
+    }
 }
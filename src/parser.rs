@@ -4,43 +4,233 @@ use nom::character::complete::*;
 use nom::combinator::*;
 use nom::multi::*;
 use nom::sequence::*;
-use nom::{
-    error::{ErrorKind, ParseError},
-    IResult, InputLength,
-};
+use nom::{error::ErrorKind, IResult, InputLength};
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Location of a node in the source given to `parse`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Hash)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset after the last character.
+    pub end: usize,
+    /// One-based line of the first character.
+    pub line: usize,
+    /// One-based column of the first character counted in characters.
+    pub column: usize,
+}
+
+impl Span {
+    /// Span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+// Equality and hashing of nodes ignore spans, so that the same code written in different places
+// compares equal.
+
+#[derive(Debug, Clone)]
+pub struct Ident(pub String, pub Span);
+
+impl PartialEq for Ident {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
 
-use std::cell::RefCell;
-use std::collections::HashSet;
+impl Eq for Ident {}
 
-#[derive(PartialEq, Eq, Debug, Clone, Hash)]
-pub struct Ident(pub String);
+impl Hash for Ident {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    /// Whether expressions are equal up to consistent renaming of the functions defined in them.
+    ///
+    /// Calls of functions defined elsewhere have to use the same names.
+    pub fn alpha_eq(&self, other: &Expr) -> bool {
+        Renaming::default().local(self, other)
+    }
+}
+
+/// Correspondence between the names defined in two expressions compared by `Expr::alpha_eq`.
+///
+/// Like the functions themselves, names are bound in the frame of the scope or function body
+/// defining them once the definition is reached. Corresponding definitions share a binding.
+#[derive(Default)]
+struct Renaming<'a> {
+    lhs: Vec<HashMap<&'a str, usize>>,
+    rhs: Vec<HashMap<&'a str, usize>>,
+    bindings: usize,
+}
+
+impl<'a> Renaming<'a> {
+    fn binding(frames: &[HashMap<&'a str, usize>], name: &Ident) -> Option<usize> {
+        frames
+            .iter()
+            .rev()
+            .find_map(|frame| frame.get(name.0.as_str()).copied())
+    }
+
+    fn ident(&self, lhs: &Ident, rhs: &Ident) -> bool {
+        match (Self::binding(&self.lhs, lhs), Self::binding(&self.rhs, rhs)) {
+            (None, None) => lhs == rhs,
+            (lhs, rhs) => lhs == rhs,
+        }
+    }
+
+    fn bind(&mut self, lhs: &'a Ident, rhs: &'a Ident) {
+        self.bindings += 1;
+        self.lhs.last_mut().unwrap().insert(&lhs.0, self.bindings);
+        self.rhs.last_mut().unwrap().insert(&rhs.0, self.bindings);
+    }
+
+    /// Compares expressions whose definitions stay local to them.
+    fn local(&mut self, lhs: &'a Expr, rhs: &'a Expr) -> bool {
+        self.lhs.push(HashMap::new());
+        self.rhs.push(HashMap::new());
+        let eq = self.expr(lhs, rhs);
+        self.lhs.pop();
+        self.rhs.pop();
+        eq
+    }
+
+    fn exprs(&mut self, lhs: &'a [Expr], rhs: &'a [Expr]) -> bool {
+        lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(l, r)| self.expr(l, r))
+    }
+
+    fn expr(&mut self, lhs: &'a Expr, rhs: &'a Expr) -> bool {
+        use self::ExprKind::*;
+        match (&lhs.kind, &rhs.kind) {
+            (Scope(l), Scope(r)) | (Lambda(l), Lambda(r)) => self.local(l, r),
+            (WriteIO(l), WriteIO(r)) => self.expr(l, r),
+            (Op(l_lhs, l_op, l_rhs), Op(r_lhs, r_op, r_rhs)) => {
+                l_op == r_op && self.expr(l_lhs, r_lhs) && self.expr(l_rhs, r_rhs)
+            }
+            (
+                Conditional {
+                    condition: l_condition,
+                    success: l_success,
+                    failure: l_failure,
+                    ..
+                },
+                Conditional {
+                    condition: r_condition,
+                    success: r_success,
+                    failure: r_failure,
+                    ..
+                },
+            ) => {
+                self.expr(l_condition, r_condition)
+                    && self.expr(l_success, r_success)
+                    && match (&**l_failure, &**r_failure) {
+                        (Some(l), Some(r)) => self.expr(l, r),
+                        (l, r) => l.is_none() && r.is_none(),
+                    }
+            }
+            (Definition(l_name, l_body), Definition(r_name, r_body)) => {
+                self.bind(l_name, r_name);
+                self.local(l_body, r_body)
+            }
+            (Call(l_name, l_params), Call(r_name, r_params)) => {
+                self.exprs(l_params, r_params) && self.ident(l_name, r_name)
+            }
+            (Apply(l_callee, l_params), Apply(r_callee, r_params)) => {
+                self.expr(l_callee, r_callee) && self.exprs(l_params, r_params)
+            }
+            (l, r) => l == r,
+        }
+    }
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl From<ExprKind> for Expr {
+    fn from(kind: ExprKind) -> Self {
+        Expr::new(kind, Span::default())
+    }
+}
 
 #[derive(PartialEq, Debug, Clone)]
-pub enum Expr {
+pub enum ExprKind {
     Scope(Box<Expr>),
     Op(Box<Expr>, Op, Box<Expr>),
     Conditional {
+        /// Index of the phrase in `IFS` that starts the conditional.
+        phrase: usize,
         condition: Box<Expr>,
         success: Box<Expr>,
+        /// Index of the phrase in `ELSES` that starts the failure branch, if there is one.
+        else_phrase: usize,
         failure: Box<Option<Expr>>,
     },
     Definition(Ident, Box<Expr>),
     Call(Ident, Vec<Expr>),
+    Lambda(Box<Expr>),
+    Apply(Box<Expr>, Vec<Expr>),
     Param(Param),
     Text(String),
     Vector(Vec<VectorComponent>),
     WriteIO(Box<Expr>),
-    ReadIO,
+    ReadIO(ReadMode),
 }
 
 #[derive(PartialEq, Debug, Clone)]
+pub enum ReadMode {
+    Line,
+    All,
+    Numbers,
+}
+
+#[derive(Debug, Clone)]
 pub enum VectorComponent {
-    Number(Number),
+    Number(Number, Span),
     Param(Param),
 }
 
-#[derive(PartialEq, Debug, Clone)]
-pub struct Param(pub Number);
+impl PartialEq for VectorComponent {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (VectorComponent::Number(lhs, _), VectorComponent::Number(rhs, _)) => lhs == rhs,
+            (VectorComponent::Param(lhs), VectorComponent::Param(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Param(pub Number, pub Span);
+
+impl PartialEq for Param {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Op {
@@ -50,7 +240,68 @@ pub enum Op {
     Sub,
 }
 
-type Number = u64;
+impl Op {
+    /// Binding power of the operator, operators with higher precedence group first.
+    ///
+    /// Operators with the same precedence are left associative.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Op::Equ => 1,
+            Op::Add | Op::Sub => 2,
+            Op::Mul => 3,
+        }
+    }
+}
+
+pub type Number = u64;
+
+/// Number of arguments a call of a function takes.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Arity {
+    Fixed(Number),
+    /// Functions using `\\` take every following expression.
+    Variadic,
+}
+
+impl Arity {
+    /// Arity of function with `body`, which is given by the highest param used in it. This includes
+    /// nested functions, which see the params of the call creating them when used as values.
+    pub fn of(body: &Expr) -> Arity {
+        use self::ExprKind::*;
+        let all = |exprs: &[Expr]| exprs.iter().map(Arity::of).max().unwrap_or(Arity::Fixed(0));
+        match &body.kind {
+            Scope(e) | WriteIO(e) | Definition(_, e) | Lambda(e) => Arity::of(e),
+            Op(lhs, _, rhs) => Arity::of(lhs).max(Arity::of(rhs)),
+            Conditional {
+                condition,
+                success,
+                failure,
+                ..
+            } => Arity::of(condition)
+                .max(Arity::of(success))
+                .max((**failure).as_ref().map_or(Arity::Fixed(0), Arity::of)),
+            Call(_, params) => all(params),
+            Apply(callee, params) => Arity::of(callee).max(all(params)),
+            Param(param) => Arity::of_param(param),
+            Vector(components) => components
+                .iter()
+                .map(|c| match c {
+                    VectorComponent::Number(..) => Arity::Fixed(0),
+                    VectorComponent::Param(param) => Arity::of_param(param),
+                })
+                .max()
+                .unwrap_or(Arity::Fixed(0)),
+            Text(_) | ReadIO(_) => Arity::Fixed(0),
+        }
+    }
+
+    fn of_param(param: &Param) -> Arity {
+        match param.0 {
+            0 => Arity::Variadic,
+            n => Arity::Fixed(n),
+        }
+    }
+}
 
 pub const IFS: &[&str] = &[
     "given that",
@@ -82,9 +333,19 @@ pub const ELSES: &[&str] = &[
     "variously",
 ];
 
+#[derive(Clone)]
 pub struct State {
     used_conditionals: RefCell<HashSet<usize>>,
     used_elses: RefCell<HashSet<usize>>,
+    // Arities of the functions defined so far, which limit the arguments their calls take. Like
+    // the functions themselves, they are local to the scope or function body defining them.
+    arities: RefCell<Vec<HashMap<String, Arity>>>,
+    // Whether vectors end after one component, so that arguments of calls with known arity don't
+    // run into each other.
+    single_components: Cell<bool>,
+    source: RefCell<String>,
+    // Byte offsets at which the lines of `source` start.
+    line_starts: RefCell<Vec<usize>>,
 }
 
 impl Default for State {
@@ -92,19 +353,176 @@ impl Default for State {
         State {
             used_conditionals: RefCell::new(HashSet::new()),
             used_elses: RefCell::new(HashSet::new()),
+            arities: RefCell::new(vec![HashMap::new()]),
+            single_components: Cell::new(false),
+            source: RefCell::new(String::new()),
+            line_starts: RefCell::new(vec![0]),
+        }
+    }
+}
+
+impl State {
+    fn arity(&self, name: &str) -> Option<Arity> {
+        self.arities
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|arities| arities.get(name).copied())
+    }
+
+    fn define(&self, name: &str, arity: Arity) {
+        let mut arities = self.arities.borrow_mut();
+        arities.last_mut().unwrap().insert(name.into(), arity);
+    }
+
+    fn set_source(&self, code: &str) {
+        *self.source.borrow_mut() = code.into();
+        *self.line_starts.borrow_mut() = Some(0)
+            .into_iter()
+            .chain(code.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+    }
+
+    /// Span of the code between remaining inputs `start` and `end` without trailing whitespace.
+    fn span(&self, start: &str, end: &str) -> Span {
+        let source = self.source.borrow();
+        let line_starts = self.line_starts.borrow();
+        let consumed = &start[..start.len() - end.len()];
+        let start = source.len().saturating_sub(start.len());
+        let line = line_starts.partition_point(|&s| s <= start);
+        let line_start = line_starts[line - 1];
+        Span {
+            start,
+            end: start + consumed.trim_end().len(),
+            line,
+            column: source
+                .get(line_start..start)
+                .map_or(0, |s| s.chars().count())
+                + 1,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Reason {
+    Expected(&'static str),
+    ReusedConditional(&'static str),
+    ReusedElse(&'static str),
+    Unexpected(ErrorKind),
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Expected(what) => write!(fmt, "expected {}", what),
+            Reason::ReusedConditional(phrase) => {
+                write!(fmt, "reused conditional phrase `{}`", phrase)
+            }
+            Reason::ReusedElse(phrase) => write!(fmt, "reused else phrase `{}`", phrase),
+            Reason::Unexpected(kind) => write!(fmt, "unexpected input ({:?})", kind),
         }
     }
 }
 
+/// Error produced by the parser combinators, pointing to the remaining input where it happened.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Error<'a> {
+    pub input: &'a str,
+    pub reason: Reason,
+}
+
+impl<'a> Error<'a> {
+    fn new(input: &'a str, reason: Reason) -> Self {
+        Error { input, reason }
+    }
+
+    fn expected(input: &'a str, what: &'static str) -> Self {
+        Error::new(input, Reason::Expected(what))
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for Error<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        Error::new(input, Reason::Unexpected(kind))
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+/// Parse error located in the original source.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ParseError {
+    pub reason: Reason,
+    /// Byte offset of the error.
+    pub offset: usize,
+    /// One-based line of the error.
+    pub line: usize,
+    /// One-based column of the error counted in characters.
+    pub column: usize,
+    /// The offending part of the source.
+    pub snippet: String,
+    /// The whole source line containing the error.
+    pub source_line: String,
+}
+
+impl ParseError {
+    pub fn new(code: &str, error: Error) -> Self {
+        let offset = code.len() - error.input.len();
+        let line_start = code[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let source_line = code[line_start..].lines().next().unwrap_or("");
+        // Reused phrases are underlined whole even though they contain whitespace.
+        let snippet = match error.reason {
+            Reason::ReusedConditional(phrase) | Reason::ReusedElse(phrase) => phrase,
+            _ => error.input.split(char::is_whitespace).next().unwrap_or(""),
+        };
+        ParseError {
+            reason: error.reason,
+            offset,
+            line: code[..offset].matches('\n').count() + 1,
+            column: code[line_start..offset].chars().count() + 1,
+            snippet: snippet.into(),
+            source_line: source_line.into(),
+        }
+    }
+
+    /// Renders the error as a diagnostic with the offending snippet underlined.
+    pub fn render(&self, path: &str) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "error: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+            self.reason,
+            gutter,
+            path,
+            self.line,
+            self.column,
+            gutter,
+            number,
+            self.source_line,
+            gutter,
+            " ".repeat(self.column - 1),
+            "^".repeat(self.snippet.chars().count().max(1)),
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}: {}", self.line, self.column, self.reason)
+    }
+}
+
 //🆘
 
 pub fn ws<'a, T: 'a>(
-    parser: impl Fn(&'a str) -> IResult<&'a str, T>,
-) -> impl Fn(&'a str) -> IResult<&'a str, T> {
+    parser: impl Fn(&'a str) -> IResult<&'a str, T, Error<'a>>,
+) -> impl Fn(&'a str) -> IResult<&'a str, T, Error<'a>> {
     terminated(parser, space0)
 }
 
-pub fn eof<I: Copy + InputLength, E: ParseError<I>>(input: I) -> IResult<I, I, E> {
+pub fn eof<I: Copy + InputLength, E: nom::error::ParseError<I>>(input: I) -> IResult<I, I, E> {
     if input.input_len() == 0 {
         Ok((input, input))
     } else {
@@ -114,155 +532,255 @@ pub fn eof<I: Copy + InputLength, E: ParseError<I>>(input: I) -> IResult<I, I, E
 
 pub fn cond<'a>(
     used: &'a RefCell<HashSet<usize>>,
-    variants: &'a [&'a str],
-) -> impl Fn(&str) -> IResult<&str, &str> + 'a {
+    variants: &'static [&'static str],
+    reused: fn(&'static str) -> Reason,
+) -> impl Fn(&str) -> IResult<&str, usize, Error<'_>> + 'a {
     move |code| {
         if used.borrow().len() == variants.len() {
             used.borrow_mut().clear();
         }
         for (n, i) in variants.iter().enumerate() {
-            if let ok @ Ok(_) = tag(*i)(code) {
-                if used.borrow().contains(&n) {
-                    // TODO: Error handling
-                    return Err(nom::Err::Failure((code, ErrorKind::Verify)));
+            if let Ok((rest, _)) = tag::<_, _, Error>(*i)(code) {
+                if !used.borrow_mut().insert(n) {
+                    return Err(nom::Err::Failure(Error::new(code, reused(i))));
                 }
-                return ok;
+                return Ok((rest, n));
             }
         }
-        // TODO: Error handling
-        Err(nom::Err::Error((code, ErrorKind::Verify)))
+        Err(nom::Err::Error(Error::expected(code, "conditional phrase")))
     }
 }
 
-pub fn otherwise<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr> + 'a {
+/// Failure branch of a conditional together with the index of its phrase.
+pub fn otherwise<'a>(
+    state: &'a State,
+) -> impl Fn(&str) -> IResult<&str, (usize, Expr), Error<'_>> + 'a {
     move |code| {
-        let (code, _) = ws(cond(&state.used_elses, &ELSES))(code)?;
-        let (code, failure) = expr(state)(code)?;
-        Ok((code, failure))
+        let (code, phrase) = ws(cond(&state.used_elses, ELSES, Reason::ReusedElse))(code)?;
+        let (code, failure) = components(state, false, code, cut(expr(state)))?;
+        Ok((code, (phrase, failure)))
     }
 }
 
-pub fn paren_start(code: &str) -> IResult<&str, &str> {
+pub fn paren_start(code: &str) -> IResult<&str, &str, Error<'_>> {
     ws(tag("{"))(code)
 }
 
-pub fn paren_end(code: &str) -> IResult<&str, &str> {
+pub fn paren_end(code: &str) -> IResult<&str, &str, Error<'_>> {
     ws(tag(")"))(code)
 }
 
-pub fn conditional<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr> + 'a {
+pub fn conditional<'a>(
+    state: &'a State,
+) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
     move |code| {
-        let (code, _) = ws(cond(&state.used_conditionals, IFS))(code)?;
-        let (code, condition) = expr(state)(code)?;
-        let (code, success) = expr(state)(code)?;
+        let (code, phrase) = ws(cond(
+            &state.used_conditionals,
+            IFS,
+            Reason::ReusedConditional,
+        ))(code)?;
+        let (code, (condition, success)) =
+            components(state, false, code, pair(cut(expr(state)), cut(expr(state))))?;
         let (code, failure) = opt(otherwise(state))(code)?;
+        let (else_phrase, failure) = match failure {
+            Some((else_phrase, failure)) => (else_phrase, Some(failure)),
+            None => (0, None),
+        };
         Ok((
             code,
-            Expr::Conditional {
+            ExprKind::Conditional {
+                phrase,
                 condition: Box::new(condition),
                 success: Box::new(success),
+                else_phrase,
                 failure: Box::new(failure),
             },
         ))
     }
 }
 
-pub fn number(code: &str) -> IResult<&str, Number> {
+pub fn number(code: &str) -> IResult<&str, Number, Error<'_>> {
     let (code, s) = alt((tag("."), tag(":")))(code)?;
     let n = if s == "." { 1 } else { 2 };
     let (code, m) = many0_count(tag(":"))(code)?;
     Ok((code, n + 2 * m as Number))
 }
 
-pub fn param(code: &str) -> IResult<&str, Param> {
-    let (code, _) = tag(r"\")(code)?;
-    let (code, t) = opt(tag(r"\"))(code)?;
-    if t.is_some() {
-        Ok((code, Param(0)))
-    } else {
-        let (code, n) = number(code)?;
-        Ok((code, Param(n)))
+pub fn param<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Param, Error<'_>> + 'a {
+    move |start| {
+        let (code, _) = tag(r"\")(start)?;
+        let (code, t) = opt(tag(r"\"))(code)?;
+        let (code, n) = if t.is_some() {
+            (code, 0)
+        } else {
+            number(code)?
+        };
+        Ok((code, Param(n, state.span(start, code))))
     }
 }
 
-pub fn vector(code: &str) -> IResult<&str, Expr> {
-    let (code, v) = separated_nonempty_list(
-        space1,
-        alt((
-            map(number, VectorComponent::Number),
-            map(param, VectorComponent::Param),
-        )),
-    )(code)?;
-    Ok((code, Expr::Vector(v)))
+pub fn vector<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
+    move |code| {
+        let component = alt((
+            |start| {
+                let (code, n) = number(start)?;
+                Ok((code, VectorComponent::Number(n, state.span(start, code))))
+            },
+            map(param(state), VectorComponent::Param),
+        ));
+        let (code, v) = if state.single_components.get() {
+            map(component, |c| vec![c])(code)?
+        } else {
+            separated_nonempty_list(space1, component)(code)?
+        };
+        Ok((code, ExprKind::Vector(v)))
+    }
 }
 
-pub fn ident(code: &str) -> IResult<&str, Ident> {
-    map(
-        take_while1(|c: char| !c.is_ascii() && c.is_alphanumeric()),
-        |s: &str| Ident(s.into()),
-    )(code)
+/// Names are made of any characters outside ASCII, such as letters and emoji, except for whitespace
+/// and `¤`.
+pub fn is_ident_char(c: char) -> bool {
+    !c.is_ascii() && !c.is_whitespace() && c != '¤'
 }
 
-pub fn fundef<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr> + 'a {
+pub fn ident<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Ident, Error<'_>> + 'a {
+    move |start| {
+        let (code, name) = take_while1(is_ident_char)(start)?;
+        Ok((code, Ident(name.into(), state.span(start, code))))
+    }
+}
+
+pub fn fundef<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
     move |code| {
-        let (code, name) = ws(ident)(code)?;
+        let (code, name) = ws(ident(state))(code)?;
         let (code, _) = ws(tag("¤"))(code)?;
-        let (code, body) = expr(state)(code)?;
-        Ok((code, Expr::Definition(name, Box::new(body))))
+        let (code, body) = local(state, code, |code| {
+            // Calls in the body, such as recursive ones, can't know the arity of the function yet.
+            state.define(&name.0, Arity::Variadic);
+            components(state, false, code, cut(expr(state)))
+        })?;
+        state.define(&name.0, Arity::of(&body));
+        Ok((code, ExprKind::Definition(name, Box::new(body))))
     }
 }
 
-pub fn funcall<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr> + 'a {
+pub fn funcall<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
     move |code| {
-        let (code, name) = ws(ident)(code)?;
-        let (code, params) = many0(expr(state))(code)?;
-        Ok((code, Expr::Call(name, params)))
+        let (code, name) = ws(ident(state))(code)?;
+        // Calls of functions that aren't defined yet take every following expression.
+        let (code, params) = match state.arity(&name.0) {
+            Some(Arity::Fixed(n)) => {
+                let count = usize::try_from(n).unwrap_or(usize::MAX);
+                components(state, true, code, many_m_n(0, count, expr(state)))?
+            }
+            _ => components(state, false, code, many0(expr(state)))?,
+        };
+        Ok((code, ExprKind::Call(name, params)))
     }
 }
 
-pub fn scope<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr> + 'a {
+pub fn lambda<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
     move |code| {
-        let (code, _) = paren_start(code)?;
-        let (code, body) = expr(state)(code)?;
-        let (code, _) = alt((paren_end, peek(line_ending), eof))(code)?;
-        Ok((code, Expr::Scope(Box::new(body))))
+        let (code, _) = ws(tag("¤"))(code)?;
+        let (code, body) = local(state, code, |code| {
+            components(state, false, code, cut(expr(state)))
+        })?;
+        Ok((code, ExprKind::Lambda(Box::new(body))))
     }
 }
 
-pub fn oper<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, (Op, Box<Expr>)> + 'a {
+pub fn apply<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
     move |code| {
-        let (code, op) = ws(alt((
-            map(tag("="), |_| Op::Equ),
-            map(tag("*"), |_| Op::Mul),
-            map(tag("+"), |_| Op::Add),
-            map(tag("-"), |_| Op::Sub),
-        )))(code)?;
-        let (code, rhs) = expr(state)(code)?;
-        Ok((code, (op, Box::new(rhs))))
+        let (start, _) = ws(tag("!"))(code)?;
+        let (code, callee) = ws(alt((map(param(state), ExprKind::Param), scope(state))))(start)
+            .map_err(|_| nom::Err::Failure(Error::expected(start, "function to apply")))?;
+        let callee = Expr::new(callee, state.span(start, code));
+        let (code, params) = components(state, false, code, many0(expr(state)))?;
+        Ok((code, ExprKind::Apply(Box::new(callee), params)))
     }
 }
 
-pub fn write_io<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr> + 'a {
+pub fn scope<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
+    move |code| {
+        let (code, _) = paren_start(code)?;
+        let (code, body) = local(state, code, |code| {
+            components(state, false, code, cut(expr(state)))
+        })?;
+        let (code, _) = alt((paren_end, peek(line_ending), eof))(code)
+            .map_err(|_| nom::Err::Failure(Error::expected(code, "closing `)` for scope")))?;
+        Ok((code, ExprKind::Scope(Box::new(body))))
+    }
+}
+
+/// Runs `parser` on `code` with arities of its own, which are dropped again once it's done.
+fn local<'c, O>(
+    state: &State,
+    code: &'c str,
+    parser: impl Fn(&'c str) -> IResult<&'c str, O, Error<'c>>,
+) -> IResult<&'c str, O, Error<'c>> {
+    state.arities.borrow_mut().push(HashMap::new());
+    let result = parser(code);
+    state.arities.borrow_mut().pop();
+    result
+}
+
+/// Runs `parser` on `code` with vectors ending after one component or not.
+fn components<'c, O>(
+    state: &State,
+    single: bool,
+    code: &'c str,
+    parser: impl Fn(&'c str) -> IResult<&'c str, O, Error<'c>>,
+) -> IResult<&'c str, O, Error<'c>> {
+    let outer = state.single_components.replace(single);
+    let result = parser(code);
+    state.single_components.set(outer);
+    result
+}
+
+pub fn operator(code: &str) -> IResult<&str, Op, Error<'_>> {
+    ws(alt((
+        map(tag("="), |_| Op::Equ),
+        map(tag("*"), |_| Op::Mul),
+        map(tag("+"), |_| Op::Add),
+        map(tag("-"), |_| Op::Sub),
+    )))(code)
+}
+
+pub fn write_io<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, ExprKind, Error<'_>> + 'a {
     move |code| {
         let (code, _) = ws(tag("@"))(code)?;
         let (code, _) = ws(tag("<<"))(code)?;
-        let (code, rhs) = expr(state)(code)?;
-        Ok((code, Expr::WriteIO(Box::new(rhs))))
+        let (code, rhs) = components(state, false, code, cut(expr(state)))?;
+        Ok((code, ExprKind::WriteIO(Box::new(rhs))))
     }
 }
 
+pub fn read_io(code: &str) -> IResult<&str, ExprKind, Error<'_>> {
+    let (code, _) = ws(tag("@"))(code)?;
+    let (code, mode) = ws(alt((
+        map(tag(">>>"), |_| ReadMode::All),
+        map(preceded(ws(tag(">>")), tag("#")), |_| ReadMode::Numbers),
+        map(tag(">>"), |_| ReadMode::Line),
+    )))(code)?;
+    Ok((code, ExprKind::ReadIO(mode)))
+}
+
 pub fn is_text_ending_char(c: char) -> bool {
     c == ' ' || c == '\r' || c == '\n'
 }
 
-pub fn text(mut code1: &str) -> IResult<&str, Expr> {
+pub fn text(mut code1: &str) -> IResult<&str, ExprKind, Error<'_>> {
     let mut text = String::new();
     loop {
         let (code, _) = tag("/")(code1)?;
         let (code, t) = opt(tag("/"))(code)?;
-        t.map(|t| text.push_str(t));
+        if let Some(t) = t {
+            text.push_str(t);
+        }
         let (code, t) = opt(tag(" "))(code)?;
-        t.map(|t| text.push_str(t));
+        if let Some(t) = t {
+            text.push_str(t);
+        }
 
         let (code, part) = take_while(|c: char| c != '/' && !is_text_ending_char(c))(code)?;
         text.push_str(part);
@@ -277,42 +795,92 @@ pub fn text(mut code1: &str) -> IResult<&str, Expr> {
         }
     }
     if text.is_empty() {
-        // TODO: Error handling
-        Err(nom::Err::Error((code1, ErrorKind::Verify)))
+        Err(nom::Err::Failure(Error::expected(code1, "text after `/`")))
     } else {
-        Ok((code1, Expr::Text(text)))
+        Ok((code1, ExprKind::Text(text)))
     }
 }
 
-pub fn expr<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr> + 'a {
+pub fn operand<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr, Error<'_>> + 'a {
     move |code| {
-        let (code, expr) = ws(alt((
+        let (rest, kind) = ws(alt((
             fundef(state),
             funcall(state),
+            lambda(state),
+            apply(state),
             text,
             write_io(state),
+            read_io,
             scope(state),
             conditional(state),
-            map(param, Expr::Param),
-            vector,
-        )))(code)?;
+            map(param(state), ExprKind::Param),
+            vector(state),
+        )))(code)
+        .map_err(|e| match e {
+            nom::Err::Error(_) => nom::Err::Error(Error::expected(code, "expression")),
+            e => e,
+        })?;
+        Ok((rest, Expr::new(kind, state.span(code, rest))))
+    }
+}
 
-        let (code, oper) = opt(oper(state))(code)?;
-        Ok((
-            code,
-            if let Some((op, rhs)) = oper {
-                Expr::Op(Box::new(expr), op, rhs)
-            } else {
-                expr
-            },
-        ))
+/// Parses chain of operations whose operators bind at least as tightly as `min_precedence`.
+pub fn operation<'a>(
+    state: &'a State,
+    min_precedence: u8,
+) -> impl Fn(&str) -> IResult<&str, Expr, Error<'_>> + 'a {
+    move |code| {
+        let (mut code, mut lhs) = operand(state)(code)?;
+        loop {
+            let (rest, op) = match operator(code) {
+                Ok((rest, op)) if op.precedence() >= min_precedence => (rest, op),
+                Ok(_) | Err(nom::Err::Error(_)) => return Ok((code, lhs)),
+                Err(e) => return Err(e),
+            };
+            let (rest, rhs) = cut(operation(state, op.precedence() + 1))(rest)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expr::new(ExprKind::Op(Box::new(lhs), op, Box::new(rhs)), span);
+            code = rest;
+        }
+    }
+}
+
+/// Parses one expression.
+///
+/// Spans are located in the source last given to `parse` with the same state, so expressions
+/// parsed on their own get spans relative to an empty source.
+pub fn expr<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Expr, Error<'_>> + 'a {
+    operation(state, 0)
+}
+
+pub fn program<'a>(state: &'a State) -> impl Fn(&str) -> IResult<&str, Vec<Expr>, Error<'_>> + 'a {
+    move |code| {
+        let mut exprs = vec![];
+        let mut separated = true;
+        let (mut code, _) = multispace0(code)?;
+        while !code.is_empty() {
+            let (rest, e) = expr(state)(code)?;
+            if !separated {
+                return Err(nom::Err::Error(Error::expected(
+                    code,
+                    "whitespace between expressions",
+                )));
+            }
+            exprs.push(e);
+            let (rest, space) = multispace0(rest)?;
+            separated = !space.is_empty() || code[..code.len() - rest.len()].ends_with(' ');
+            code = rest;
+        }
+        Ok((code, exprs))
     }
 }
 
-pub fn parse<'a>(state: &State, code: &'a str) -> IResult<&'a str, Vec<Expr>> {
-    delimited(
-        multispace0,
-        separated_list(multispace1, expr(state)),
-        multispace0,
-    )(code)
+pub fn parse(state: &State, code: &str) -> Result<Vec<Expr>, ParseError> {
+    state.set_source(code);
+    program(state)(code)
+        .map(|(_, exprs)| exprs)
+        .map_err(|e| match e {
+            nom::Err::Error(e) | nom::Err::Failure(e) => ParseError::new(code, e),
+            nom::Err::Incomplete(_) => ParseError::new(code, Error::expected("", "more input")),
+        })
 }
@@ -0,0 +1,175 @@
+use crate::parser::{Expr, ExprKind, Op, Param, ReadMode, VectorComponent, ELSES, IFS};
+
+/// Renders program in canonical layout with one top-level expression per line.
+///
+/// Conditionals keep the phrases they were written with. Printing an AST produced by the parser
+/// gives source that parses back to it.
+pub fn print(ast: &[Expr]) -> String {
+    let mut printer = Printer::default();
+    for expr in ast {
+        printer.expr(expr);
+        if !printer.line_closed {
+            printer.out.push('\n');
+        }
+        printer.after_text = false;
+        printer.line_closed = false;
+    }
+    printer.out
+}
+
+pub fn print_expr(expr: &Expr) -> String {
+    let mut printer = Printer::default();
+    printer.expr(expr);
+    printer.out
+}
+
+pub fn print_number(n: u64) -> String {
+    let (first, rest) = if n % 2 == 1 {
+        (".", n / 2)
+    } else {
+        (":", (n / 2).saturating_sub(1))
+    };
+    format!("{}{}", first, ":".repeat(rest as usize))
+}
+
+pub fn print_param(param: &Param) -> String {
+    match param.0 {
+        0 => r"\\".into(),
+        n => format!(r"\{}", print_number(n)),
+    }
+}
+
+pub fn print_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        out.push('/');
+        if rest.starts_with('/') {
+            out.push('/');
+            rest = &rest[1..];
+        }
+        if rest.starts_with(' ') {
+            out.push(' ');
+            rest = &rest[1..];
+        }
+        let end = rest.find(['/', ' ']).unwrap_or(rest.len());
+        out.push_str(&rest[..end]);
+        rest = &rest[end..];
+    }
+    out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    // Text continues until whitespace so anything following it has to be separated.
+    after_text: bool,
+    // Text ending in `/` would take a following space as part of it, so it can only be followed
+    // by a line ending, which closes every open scope.
+    line_closed: bool,
+}
+
+impl Printer {
+    fn push(&mut self, s: &str) {
+        self.out.push_str(s);
+        self.after_text = false;
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Scope(body) => {
+                self.push("{");
+                self.expr(body);
+                if self.line_closed {
+                    return;
+                }
+                if self.after_text && self.out.ends_with('/') {
+                    self.push("\n");
+                    self.line_closed = true;
+                    return;
+                }
+                if self.after_text {
+                    self.push(" ");
+                }
+                self.push(")");
+            }
+            ExprKind::Op(lhs, op, rhs) => {
+                self.expr(lhs);
+                self.push(match op {
+                    Op::Equ => " = ",
+                    Op::Add => " + ",
+                    Op::Mul => " * ",
+                    Op::Sub => " - ",
+                });
+                self.expr(rhs);
+            }
+            ExprKind::Conditional {
+                phrase,
+                condition,
+                success,
+                else_phrase,
+                failure,
+            } => {
+                self.push(IFS[*phrase]);
+                self.push(" ");
+                self.expr(condition);
+                self.push(" ");
+                self.expr(success);
+                if let Some(failure) = &**failure {
+                    self.push(" ");
+                    self.push(ELSES[*else_phrase]);
+                    self.push(" ");
+                    self.expr(failure);
+                }
+            }
+            ExprKind::Definition(name, body) => {
+                self.push(&name.0);
+                self.push(" ¤ ");
+                self.expr(body);
+            }
+            ExprKind::Call(name, params) => {
+                self.push(&name.0);
+                for param in params {
+                    self.push(" ");
+                    self.expr(param);
+                }
+            }
+            ExprKind::Lambda(body) => {
+                self.push("¤ ");
+                self.expr(body);
+            }
+            ExprKind::Apply(callee, params) => {
+                self.push("! ");
+                self.expr(callee);
+                for param in params {
+                    self.push(" ");
+                    self.expr(param);
+                }
+            }
+            ExprKind::Param(param) => self.push(&print_param(param)),
+            ExprKind::Text(text) => {
+                self.push(&print_text(text));
+                self.after_text = true;
+            }
+            ExprKind::Vector(components) => {
+                let components = components
+                    .iter()
+                    .map(|c| match c {
+                        VectorComponent::Number(n, _) => print_number(*n),
+                        VectorComponent::Param(p) => print_param(p),
+                    })
+                    .collect::<Vec<_>>();
+                self.push(&components.join(" "));
+            }
+            ExprKind::WriteIO(src) => {
+                self.push("@ << ");
+                self.expr(src);
+            }
+            ExprKind::ReadIO(mode) => self.push(match mode {
+                ReadMode::Line => "@ >>",
+                ReadMode::All => "@ >>>",
+                ReadMode::Numbers => "@ >> #",
+            }),
+        }
+    }
+}
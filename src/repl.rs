@@ -0,0 +1,149 @@
+use sos::interpreter::{self, Value};
+use sos::parser::{self, ParseError};
+use sos::printer;
+
+use std::fs;
+use std::io::{self, Write};
+
+const PROMPT: &str = "🆘> ";
+const CONTINUATION: &str = "... ";
+
+const HELP: &str = "\
+:defs         List functions defined at the top level
+:reset        Forget all definitions and used conditional phrases
+:load <file>  Run a file in the current session
+:help         Show this help
+:quit         Exit";
+
+struct Session {
+    parser: parser::State,
+    interpreter: interpreter::State,
+}
+
+impl Session {
+    /// Creates session whose prompt and `@ >>` read the same stdin, so neither blocks the other.
+    fn new() -> Self {
+        Session {
+            parser: parser::State::default(),
+            interpreter: interpreter::State::with_io(io::stdin().lock(), io::stdout()),
+        }
+    }
+
+    fn parse(&mut self, code: &str) -> Result<Vec<parser::Expr>, ParseError> {
+        let snapshot = self.parser.clone();
+        let res = parser::parse(&self.parser, code);
+        if res.is_err() {
+            self.parser = snapshot;
+        }
+        res
+    }
+
+    fn eval(&mut self, ast: &[parser::Expr]) {
+        match interpreter::interpret(&mut self.interpreter, ast) {
+            Ok(Value::Empty) => {}
+            Ok(value) => println!("{}", value),
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+
+    fn command(&mut self, command: &str) -> bool {
+        let mut parts = command.splitn(2, char::is_whitespace);
+        match (parts.next().unwrap_or(""), parts.next().map(str::trim)) {
+            (":quit", _) | (":q", _) => return false,
+            (":help", _) => println!("{}", HELP),
+            (":reset", _) => {
+                self.parser = parser::State::default();
+                self.interpreter.clear_definitions();
+            }
+            (":defs", _) => {
+                let mut defs = self.interpreter.definitions();
+                defs.sort_unstable_by(|(lhs, _), (rhs, _)| lhs.0.cmp(&rhs.0));
+                for (name, body) in defs {
+                    println!("{} ¤ {}", name.0, printer::print_expr(&body));
+                }
+            }
+            (":load", Some(path)) if !path.is_empty() => match fs::read_to_string(path) {
+                Ok(code) => match self.parse(&code) {
+                    Ok(ast) => self.eval(&ast),
+                    Err(e) => eprintln!("{}", e.render(path)),
+                },
+                Err(e) => eprintln!("error: Couldn't read `{}`: {}", path, e),
+            },
+            (":load", _) => eprintln!("error: Usage `:load <file>`"),
+            (command, _) => eprintln!("error: Unknown command `{}`, try `:help`", command),
+        }
+        true
+    }
+}
+
+/// Input is incomplete if parsing failed only because it ended too early.
+fn is_incomplete(code: &str, error: &ParseError) -> bool {
+    code[error.offset..].trim().is_empty()
+}
+
+/// Number of scopes opened with `{` but not yet closed with `)`, skipping texts.
+///
+/// Scopes left open are closed at the end of the line when parsing, so they have to be counted
+/// before the input is parsed.
+fn open_scopes(code: &str) -> usize {
+    let mut open = 0usize;
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => open += 1,
+            ')' => open = open.saturating_sub(1),
+            '/' => loop {
+                chars.next_if_eq(&'/');
+                chars.next_if_eq(&' ');
+                while chars
+                    .next_if(|&c| c != '/' && !parser::is_text_ending_char(c))
+                    .is_some()
+                {}
+                if chars.next_if_eq(&'/').is_none() {
+                    break;
+                }
+            },
+            _ => {}
+        }
+    }
+    open
+}
+
+pub fn repl() -> io::Result<()> {
+    let mut session = Session::new();
+    let mut code = String::new();
+    loop {
+        print!(
+            "{}",
+            if code.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION
+            }
+        );
+        io::stdout().flush()?;
+        let line = match session.interpreter.read_line()? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        if code.is_empty() && line.trim_start().starts_with(':') {
+            if !session.command(line.trim()) {
+                return Ok(());
+            }
+            continue;
+        }
+        if !code.is_empty() {
+            code.push(' ');
+        }
+        code.push_str(&line);
+        if open_scopes(&code) > 0 && !line.trim().is_empty() {
+            continue;
+        }
+        match session.parse(&code) {
+            Ok(ast) => session.eval(&ast),
+            Err(ref e) if is_incomplete(&code, e) && !line.trim().is_empty() => continue,
+            Err(e) => eprintln!("{}", e.render("<repl>")),
+        }
+        code.clear();
+    }
+}
@@ -0,0 +1,575 @@
+use crate::interpreter::{
+    interpret_addition, interpret_multiplication, interpret_param, interpret_read,
+    interpret_subtraction, is_truthy, vectorize, Closure, ErrorKind, Result, RuntimeError, State,
+    Value,
+};
+use crate::parser::{Expr, ExprKind, Ident, Number, Op, ReadMode, VectorComponent};
+use num_bigint::BigInt;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Instruction of the stack machine. Sources given to `Code::source` name the expression that is
+/// reported if the instruction fails.
+#[derive(Debug)]
+pub enum Instr {
+    Text(String, usize),
+    Numbers(Vec<Number>, usize),
+    /// Pushes numbered argument of the innermost call.
+    Param(Number, usize),
+    /// Pushes every argument of the innermost call, which is what `\\` consists of.
+    Args(usize),
+    /// Evaluates function values among the arguments pushed by `Args` until none are left.
+    ForceArgs,
+    /// Replaces the arguments pushed by `Args` with one vector of all their components.
+    ConcatArgs(usize),
+    Empty,
+    Pop,
+    /// Replaces given number of values with one vector of all their components.
+    Concat(usize, usize),
+    /// Evaluates function value at given distance from the top until it isn't a function.
+    Force(usize),
+    Op(Op, usize),
+    Jump(usize),
+    /// Pops value and jumps unless it's truthy.
+    JumpUnless(usize),
+    Scope(Rc<Code>),
+    Define(Ident, Rc<Expr>),
+    Lambda(Rc<Expr>),
+    /// Calls named function with given number of arguments.
+    Call(Ident, usize, usize),
+    /// Fails unless the value on top is a function.
+    Callable(usize),
+    /// Applies function below given number of arguments to them.
+    Apply(usize),
+    Write(usize),
+    Read(ReadMode, usize),
+}
+
+/// Position of a compiled expression in the syntax tree.
+///
+/// Expressions are only looked up when they are reported, so compiling doesn't copy any of them.
+#[derive(Debug)]
+enum Location {
+    /// Expression at given index of the program.
+    Root(usize),
+    /// Child at given index of the expression at another location.
+    Child(Rc<Location>, usize),
+}
+
+#[derive(Debug)]
+pub struct Code {
+    pub instrs: Vec<Instr>,
+    /// Expressions whose evaluation starts at each instruction, innermost first.
+    pub starts: Vec<Vec<usize>>,
+    // Program the code is compiled from, shared with the code of the scopes in it.
+    program: Rc<[Expr]>,
+    locations: Vec<Rc<Location>>,
+}
+
+/// Compiles program so that running it leaves the value of the last expression on the stack.
+pub fn compile(ast: &[Expr]) -> Code {
+    let mut code = Code::new(ast.into());
+    if ast.is_empty() {
+        code.emit(Instr::Empty);
+    }
+    for (i, expr) in ast.iter().enumerate() {
+        if i > 0 {
+            code.emit(Instr::Pop);
+        }
+        code.expr(expr, Rc::new(Location::Root(i)));
+    }
+    code
+}
+
+pub fn compile_expr(expr: &Expr) -> Code {
+    compile(std::slice::from_ref(expr))
+}
+
+impl Code {
+    fn new(program: Rc<[Expr]>) -> Self {
+        Code {
+            instrs: vec![],
+            starts: vec![],
+            program,
+            locations: vec![],
+        }
+    }
+
+    /// Expression named by `source` in the instructions.
+    pub fn source(&self, source: usize) -> &Expr {
+        let mut path = vec![];
+        let mut location = &*self.locations[source];
+        let root = loop {
+            match location {
+                Location::Root(i) => break &self.program[*i],
+                Location::Child(parent, i) => {
+                    path.push(*i);
+                    location = parent;
+                }
+            }
+        };
+        path.into_iter().rev().fold(root, child)
+    }
+
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.instrs.push(instr);
+        self.starts.push(vec![]);
+        self.instrs.len() - 1
+    }
+
+    /// Location of the child at `index` of the expression named by `source`.
+    fn child(&self, source: usize, index: usize) -> Rc<Location> {
+        Rc::new(Location::Child(self.locations[source].clone(), index))
+    }
+
+    fn expr(&mut self, expr: &Expr, location: Rc<Location>) {
+        let start = self.instrs.len();
+        self.locations.push(location);
+        let source = self.locations.len() - 1;
+        self.expr_kind(expr, source);
+        self.starts[start].push(source);
+    }
+
+    fn expr_kind(&mut self, expr: &Expr, source: usize) {
+        match &expr.kind {
+            ExprKind::Scope(body) => {
+                let mut code = Code::new(self.program.clone());
+                code.expr(body, self.child(source, 0));
+                self.emit(Instr::Scope(Rc::new(code)));
+            }
+            ExprKind::Op(lhs, op, rhs) => {
+                self.expr(lhs, self.child(source, 0));
+                self.expr(rhs, self.child(source, 1));
+                if *op != Op::Equ {
+                    self.force(lhs, 1);
+                    self.force(rhs, 0);
+                }
+                self.emit(Instr::Op(op.clone(), source));
+            }
+            ExprKind::Conditional {
+                condition,
+                success,
+                failure,
+                ..
+            } => {
+                self.expr(condition, self.child(source, 0));
+                self.force(condition, 0);
+                let jump = self.emit(Instr::JumpUnless(0));
+                self.expr(success, self.child(source, 1));
+                let end = self.emit(Instr::Jump(0));
+                self.instrs[jump] = Instr::JumpUnless(self.instrs.len());
+                match &**failure {
+                    Some(failure) => self.expr(failure, self.child(source, 2)),
+                    None => {
+                        self.emit(Instr::Empty);
+                    }
+                }
+                self.instrs[end] = Instr::Jump(self.instrs.len());
+            }
+            ExprKind::Definition(name, body) => {
+                self.emit(Instr::Define(name.clone(), Rc::new((**body).clone())));
+            }
+            ExprKind::Call(name, params) => {
+                for (i, param) in params.iter().enumerate() {
+                    self.expr(param, self.child(source, i));
+                }
+                self.emit(Instr::Call(name.clone(), params.len(), source));
+            }
+            ExprKind::Lambda(body) => {
+                self.emit(Instr::Lambda(Rc::new((**body).clone())));
+            }
+            ExprKind::Apply(callee, params) => {
+                self.expr(callee, self.child(source, 0));
+                self.emit(Instr::Callable(source));
+                for (i, param) in params.iter().enumerate() {
+                    self.expr(param, self.child(source, i + 1));
+                }
+                self.emit(Instr::Apply(params.len()));
+            }
+            ExprKind::Param(param) => self.param(param.0, source),
+            ExprKind::Text(text) => {
+                self.emit(Instr::Text(text.clone(), source));
+            }
+            ExprKind::Vector(components) => self.vector(components, source),
+            ExprKind::WriteIO(src) => {
+                self.expr(src, self.child(source, 0));
+                self.emit(Instr::Write(source));
+            }
+            ExprKind::ReadIO(mode) => {
+                self.emit(Instr::Read(mode.clone(), source));
+            }
+        }
+    }
+
+    /// Evaluates value of `expr` at `depth` from the top unless it can't be a function.
+    fn force(&mut self, expr: &Expr, depth: usize) {
+        use crate::parser::ExprKind::*;
+        match expr.kind {
+            Op(..) | Text(_) | Vector(_) | WriteIO(_) | ReadIO(_) => {}
+            _ => {
+                self.emit(Instr::Force(depth));
+            }
+        }
+    }
+
+    fn vector(&mut self, components: &[VectorComponent], source: usize) {
+        let numbers = components
+            .iter()
+            .map(|c| match c {
+                VectorComponent::Number(n, _) => Some(*n),
+                VectorComponent::Param(_) => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        if let Some(numbers) = numbers {
+            self.emit(Instr::Numbers(numbers, source));
+            return;
+        }
+        for component in components {
+            match component {
+                VectorComponent::Number(n, _) => {
+                    self.emit(Instr::Numbers(vec![*n], source));
+                }
+                VectorComponent::Param(p) => {
+                    self.param(p.0, source);
+                    self.emit(Instr::Force(0));
+                }
+            }
+        }
+        self.emit(Instr::Concat(components.len(), source));
+    }
+
+    fn param(&mut self, param: Number, source: usize) {
+        if param == 0 {
+            self.emit(Instr::Args(source));
+            self.emit(Instr::ForceArgs);
+            self.emit(Instr::ConcatArgs(source));
+        } else {
+            self.emit(Instr::Param(param, source));
+        }
+    }
+}
+
+/// Child at `index` of `expr` as numbered by the compiler.
+fn child(expr: &Expr, index: usize) -> &Expr {
+    use crate::parser::ExprKind::*;
+    match (&expr.kind, index) {
+        (Scope(e), 0) | (WriteIO(e), 0) | (Op(e, _, _), 0) | (Op(_, _, e), 1) => e,
+        (Conditional { condition, .. }, 0) => condition,
+        (Conditional { success, .. }, 1) => success,
+        (Conditional { failure, .. }, 2) => (**failure).as_ref().expect("Missing failure"),
+        (Call(_, params), i) => &params[i],
+        (Apply(callee, _), 0) => callee,
+        (Apply(_, params), i) => &params[i - 1],
+        _ => panic!("Expression has no child {}", index),
+    }
+}
+
+/// Compiles program and runs it, returning the value of the last expression like
+/// `interpreter::interpret` does.
+pub fn run(state: &mut State, ast: &[Expr]) -> Result<Value> {
+    Machine::default().run(state, Rc::new(compile(ast)))
+}
+
+/// Number of arguments of the innermost call in `state`.
+fn args(state: &State) -> usize {
+    state.env().params().map_or(0, <[Value]>::len)
+}
+
+/// Whether running code from `ip` only jumps to its end.
+fn returns(code: &Code, mut ip: usize) -> bool {
+    loop {
+        match code.instrs.get(ip) {
+            None => return true,
+            Some(Instr::Jump(target)) => ip = *target,
+            Some(_) => return false,
+        }
+    }
+}
+
+struct Frame {
+    code: Rc<Code>,
+    ip: usize,
+    // `None` when running directly on the state given to `run`.
+    state: Option<State>,
+    // Distance from the top of the stack where the result goes once the frame returns.
+    depth: usize,
+    // Call depth of the frame that entered this one or the frames it replaced.
+    base: usize,
+    // Function body for frames that evaluate one.
+    body: Option<Rc<Expr>>,
+}
+
+impl Frame {
+    fn new(code: Rc<Code>, state: State, depth: usize) -> Self {
+        Frame {
+            code,
+            ip: 0,
+            state: Some(state),
+            depth,
+            base: 0,
+            body: None,
+        }
+    }
+
+    fn call(code: Rc<Code>, body: Rc<Expr>, state: State, depth: usize) -> Self {
+        Frame {
+            body: Some(body),
+            ..Frame::new(code, state, depth)
+        }
+    }
+
+    fn enter(&mut self, base: usize) -> Result<()> {
+        self.base = base;
+        match (&self.body, &mut self.state) {
+            (Some(body), Some(state)) => state.set_depth(base + 1).map_err(|e| e.at(body)),
+            _ => Ok(()),
+        }
+    }
+}
+
+enum Control {
+    Next,
+    Jump(usize),
+    // Continues at the next instruction once the frame returns.
+    Enter(Frame),
+    // Runs the instruction again once the frame returns.
+    Retry(Frame),
+}
+
+#[derive(Default)]
+struct Machine {
+    stack: Vec<Value>,
+    // Function bodies are kept alive so their addresses stay unique.
+    compiled: HashMap<*const Expr, (Rc<Expr>, Rc<Code>)>,
+}
+
+impl Machine {
+    fn run(&mut self, root: &mut State, code: Rc<Code>) -> Result<Value> {
+        let mut frames = vec![Frame {
+            code,
+            ip: 0,
+            state: None,
+            depth: 0,
+            base: root.depth(),
+            body: None,
+        }];
+        while let Some(frame) = frames.last_mut() {
+            if frame.ip == frame.code.instrs.len() {
+                let depth = frame.depth;
+                frames.pop();
+                let value = self.stack.pop().unwrap_or(Value::Empty);
+                let at = self.stack.len() - depth;
+                self.stack.insert(at, value);
+                continue;
+            }
+            let state = match frame.state.as_mut() {
+                Some(state) => state,
+                None => &mut *root,
+            };
+            let depth = state.depth();
+            match self.step(state, &frame.code, frame.ip)? {
+                Control::Next => frame.ip += 1,
+                Control::Jump(target) => frame.ip = target,
+                // Frames that would return right after the one entered are replaced by it, so
+                // loops written as recursion run in constant space.
+                Control::Enter(mut next) if returns(&frame.code, frame.ip + 1) => {
+                    next.enter(frame.base)?;
+                    next.depth = frame.depth;
+                    *frame = next;
+                }
+                Control::Enter(mut next) => {
+                    next.enter(depth)?;
+                    frame.ip += 1;
+                    frames.push(next);
+                }
+                Control::Retry(mut next) => {
+                    next.enter(depth)?;
+                    frames.push(next);
+                }
+            }
+        }
+        Ok(self.stack.pop().unwrap_or(Value::Empty))
+    }
+
+    fn compiled(&mut self, body: &Rc<Expr>) -> Rc<Code> {
+        self.compiled
+            .entry(Rc::as_ptr(body))
+            .or_insert_with(|| (body.clone(), Rc::new(compile_expr(body))))
+            .1
+            .clone()
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("Stack underflow")
+    }
+
+    /// Pops given number of values in the order they were pushed.
+    fn pop_many(&mut self, count: usize) -> Vec<Value> {
+        if count == 0 {
+            return vec![];
+        }
+        self.stack.split_off(self.stack.len() - count)
+    }
+
+    fn push(&mut self, value: Value) -> Control {
+        self.stack.push(value);
+        Control::Next
+    }
+
+    /// Evaluates function value at `depth` from the top, running this instruction again after.
+    fn force(&mut self, state: &State, depth: usize) -> Control {
+        let i = self.stack.len() - 1 - depth;
+        match &self.stack[i] {
+            Value::Function(_) => match self.stack.remove(i) {
+                Value::Function(closure) => Control::Retry(Frame::call(
+                    self.compiled(&closure.body),
+                    closure.body,
+                    state.enter(closure.env),
+                    depth,
+                )),
+                _ => unreachable!(),
+            },
+            _ => Control::Next,
+        }
+    }
+
+    fn concat(&mut self, state: &mut State, count: usize, source: &Expr) -> Result<Control> {
+        let parts = self.pop_many(count);
+        let mut res = vec![];
+        for part in parts {
+            res.extend(vectorize(state, part)?);
+        }
+        let value = Value::Vector(res);
+        state.check_size(&value).map_err(|e| e.at(source))?;
+        Ok(self.push(value))
+    }
+
+    fn step(&mut self, state: &mut State, code: &Code, ip: usize) -> Result<Control> {
+        let at = |i: usize| move |e: RuntimeError| e.at(code.source(i));
+        for &source in code.starts[ip].iter().rev() {
+            state.step(1).map_err(at(source))?;
+        }
+        Ok(match &code.instrs[ip] {
+            Instr::Text(text, source) => {
+                let value = Value::Text(text.clone());
+                state.check_size(&value).map_err(at(*source))?;
+                self.push(value)
+            }
+            Instr::Numbers(numbers, source) => {
+                let value = Value::Vector(
+                    numbers
+                        .iter()
+                        .map(|&n| state.wrap(BigInt::from(n)))
+                        .collect(),
+                );
+                state.check_size(&value).map_err(at(*source))?;
+                self.push(value)
+            }
+            Instr::Param(param, source) => {
+                let value = interpret_param(state, *param).map_err(at(*source))?;
+                self.push(value)
+            }
+            Instr::Empty => self.push(Value::Empty),
+            Instr::Pop => {
+                self.pop();
+                Control::Next
+            }
+            Instr::Args(source) => {
+                let params = state
+                    .env()
+                    .params()
+                    .ok_or_else(|| ErrorKind::UnboundParam(0).into())
+                    .map_err(at(*source))?;
+                self.stack.extend_from_slice(params);
+                Control::Next
+            }
+            Instr::ForceArgs => {
+                let count = args(state);
+                let args = &self.stack[self.stack.len() - count..];
+                match args.iter().position(|v| matches!(v, Value::Function(_))) {
+                    Some(i) => self.force(state, count - 1 - i),
+                    None => Control::Next,
+                }
+            }
+            Instr::ConcatArgs(source) => self.concat(state, args(state), code.source(*source))?,
+            Instr::Concat(count, source) => self.concat(state, *count, code.source(*source))?,
+            Instr::Force(depth) => self.force(state, *depth),
+            Instr::Op(op, source) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                let value = match op {
+                    Op::Equ => Ok(Value::Boolean(lhs == rhs)),
+                    Op::Add => interpret_addition(state, lhs, rhs),
+                    Op::Mul => interpret_multiplication(state, lhs, rhs),
+                    Op::Sub => interpret_subtraction(state, lhs, rhs),
+                }
+                .map_err(at(*source))?;
+                state.check_size(&value).map_err(at(*source))?;
+                self.push(value)
+            }
+            Instr::Jump(target) => Control::Jump(*target),
+            Instr::JumpUnless(target) => {
+                let condition = self.pop();
+                if is_truthy(state, condition)? {
+                    Control::Next
+                } else {
+                    Control::Jump(*target)
+                }
+            }
+            Instr::Scope(body) => Control::Enter(Frame::new(
+                body.clone(),
+                state.enter(state.env().clone()),
+                0,
+            )),
+            Instr::Define(name, body) => {
+                state.add(name.clone(), body.clone());
+                self.push(Value::Function(Closure {
+                    body: body.clone(),
+                    env: state.env().clone(),
+                }))
+            }
+            Instr::Lambda(body) => self.push(Value::Function(Closure {
+                body: body.clone(),
+                env: state.env().clone(),
+            })),
+            Instr::Call(name, count, source) => {
+                let params = self.pop_many(*count);
+                let fun = state
+                    .resolve_fun(name)
+                    .ok_or_else(|| ErrorKind::UndefinedFunction(name.clone()).into())
+                    .map_err(at(*source))?;
+                let state = state.enter(fun.env.with_params(params));
+                Control::Enter(Frame::call(self.compiled(&fun.body), fun.body, state, 0))
+            }
+            Instr::Callable(source) => match self.stack.last() {
+                Some(Value::Function(_)) => Control::Next,
+                _ => {
+                    let value = self.pop();
+                    return Err(at(*source)(ErrorKind::NotAFunction(value).into()));
+                }
+            },
+            Instr::Apply(count) => {
+                let params = self.pop_many(*count);
+                match self.pop() {
+                    Value::Function(closure) => {
+                        let state = state.enter(closure.env.with_params(params));
+                        let code = self.compiled(&closure.body);
+                        Control::Enter(Frame::call(code, closure.body, state, 0))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Instr::Write(source) => {
+                let value = self.pop();
+                state.write(&value).map_err(|e| at(*source)(e.into()))?;
+                self.push(Value::Empty)
+            }
+            Instr::Read(mode, source) => {
+                let value = interpret_read(state, mode).map_err(at(*source))?;
+                state.check_size(&value).map_err(at(*source))?;
+                self.push(value)
+            }
+        })
+    }
+}
@@ -0,0 +1,233 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+fn sos_with_input(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sos"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Running sos failed");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .expect("Writing input failed");
+    child.wait_with_output().expect("Running sos failed")
+}
+
+fn sos(args: &[&str]) -> (bool, String) {
+    let out = sos_with_input(args, "");
+    (
+        out.status.success(),
+        String::from_utf8(out.stdout).expect("Output isn't UTF-8"),
+    )
+}
+
+/// Runs `code` from a file called `name` with `args` and returns the exit code and what it printed
+/// to stdout and stderr, where the path of the file is shortened to `name`.
+fn run_code(name: &str, args: &[&str], code: &str) -> (Option<i32>, String, String) {
+    let path = env::temp_dir().join(name);
+    fs::write(&path, code).expect("Writing program failed");
+    let path = path.to_str().expect("Path isn't UTF-8");
+    let out = sos_with_input(&[&["run"], args, &[path]].concat(), "");
+    (
+        out.status.code(),
+        String::from_utf8(out.stdout).expect("Output isn't UTF-8"),
+        String::from_utf8(out.stderr)
+            .expect("Output isn't UTF-8")
+            .replace(path, name),
+    )
+}
+
+/// Runs REPL session with `input` and returns what it printed to stdout and stderr.
+fn repl(input: &str) -> (String, String) {
+    let out = sos_with_input(&["repl"], input);
+    assert!(out.status.success());
+    (
+        String::from_utf8(out.stdout).expect("Output isn't UTF-8"),
+        String::from_utf8(out.stderr).expect("Output isn't UTF-8"),
+    )
+}
+
+#[test]
+fn run_example() {
+    assert_eq!((true, "false\n".into()), sos(&["run", "example.🆘"]));
+    assert_eq!(
+        (true, "false\n".into()),
+        sos(&["run", "--vm", "example.🆘"])
+    );
+}
+
+#[test]
+fn run_with_seed_is_reproducible() {
+    let code = "@ << {. = .) + . : .: :: .:: ::: .::: :::: .:::: :::::\n";
+    for args in &[&["--seed", "7"][..], &["--vm", "--seed", "7"]] {
+        let first = run_code("seed.🆘", args, code);
+        assert_eq!(first, run_code("seed.🆘", args, code));
+        assert_eq!(
+            (Some(0), 1, "".into()),
+            (first.0, first.1.lines().count(), first.2)
+        );
+    }
+}
+
+#[test]
+fn run_reports_parse_error() {
+    assert_eq!(
+        (
+            Some(1),
+            "".into(),
+            "error: expected closing `)` for scope\n --> parse.🆘:1:4\n  |\n1 | {. /x)\n  |    ^^^\n"
+                .into()
+        ),
+        run_code("parse.🆘", &[], "{. /x)\n")
+    );
+}
+
+#[test]
+fn run_reports_runtime_error() {
+    for args in &[&[][..], &["--vm"]] {
+        assert_eq!(
+            (
+                Some(1),
+                "a\n".into(),
+                "error: Function with name `ä` wasn't defined\n --> runtime.🆘:2:1\n".into()
+            ),
+            run_code("runtime.🆘", args, "@ << /a\nä\n")
+        );
+    }
+}
+
+#[test]
+fn run_stops_runaway_recursion() {
+    let code = "ö ¤ {ö) + .\nö\n";
+    assert_eq!(
+        (
+            Some(1),
+            "".into(),
+            "error: Exceeded the limit of 10000 nested calls\n --> recursion.🆘:1:5\n".into()
+        ),
+        run_code("recursion.🆘", &[], code)
+    );
+    assert_eq!(
+        (
+            Some(1),
+            "".into(),
+            "error: Exceeded the limit of 100000 nested calls\n --> recursion.🆘:1:5\n".into()
+        ),
+        run_code("recursion.🆘", &["--max-depth", "100000"], code)
+    );
+}
+
+#[test]
+fn run_stops_at_limits() {
+    let cases = [
+        (
+            "steps.🆘",
+            "--max-steps",
+            "100",
+            "ö ¤ {@ << /tick ) + {ö)\nö\n",
+            "tick\n".repeat(16),
+            "error: Exceeded the limit of 100 steps\n --> steps.🆘:1:6\n",
+        ),
+        (
+            "depth.🆘",
+            "--max-depth",
+            "50",
+            "ö ¤ {ö) + .\nö\n",
+            "".into(),
+            "error: Exceeded the limit of 50 nested calls\n --> depth.🆘:1:5\n",
+        ),
+        (
+            "length.🆘",
+            "--max-vector-length",
+            "3",
+            "@ << . : .:\n@ << . : .: ::\n",
+            "1 2 3\n".into(),
+            "error: Exceeded the limit of 3 vector components\n --> length.🆘:2:6\n",
+        ),
+        (
+            "bits.🆘",
+            "--max-vector-bits",
+            "64",
+            "@ << . :\nö ¤ ö {\\. * \\.)\nö :\n",
+            "1 2\n".into(),
+            "error: Exceeded the limit of 64 bits of vector components\n --> bits.🆘:2:8\n",
+        ),
+        (
+            "text.🆘",
+            "--max-text-size",
+            "100",
+            "@ << /ab * :\n@ << /ab * ::::::::::::::::::::::::::::::::::::::::::::::::::\n",
+            "abab\n".into(),
+            "error: Exceeded the limit of 100 bytes of text\n --> text.🆘:2:6\n",
+        ),
+    ];
+    for (name, flag, max, code, out, err) in &cases {
+        for args in &[&[*flag, *max][..], &["--vm", *flag, *max]] {
+            assert_eq!(
+                (Some(1), out.clone(), err.to_string()),
+                run_code(name, args, code),
+                "{:?}",
+                args
+            );
+        }
+    }
+}
+
+#[test]
+fn repl_reads_input_after_the_prompt() {
+    assert_eq!(
+        ("🆘> hello\n🆘> ".into(), "".into()),
+        repl("@ << @ >>\nhello\n")
+    );
+}
+
+#[test]
+fn repl_continues_unclosed_scope() {
+    assert_eq!(
+        ("🆘> ... a b\n🆘> ".into(), "".into()),
+        repl("{/a\n+ /b )\n")
+    );
+}
+
+#[test]
+fn repl_continues_incomplete_input() {
+    assert_eq!(
+        ("🆘> ... a\n🆘> ".into(), "".into()),
+        repl("given that /Ok\n/a\n")
+    );
+}
+
+#[test]
+fn repl_lists_definitions() {
+    assert_eq!(
+        ("🆘> ¤\n🆘> ¤\n🆘> ä ¤ /x\nå ¤ /y\n🆘> ".into(), "".into()),
+        repl("ä ¤ /x\nå ¤ /y\n:defs\n")
+    );
+}
+
+#[test]
+fn repl_reset_forgets_definitions() {
+    let (out, err) = repl("ä ¤ /x\n:reset\n:defs\nä\n");
+    assert_eq!("🆘> ¤\n🆘> 🆘> 🆘> 🆘> ", out);
+    assert_eq!("error: Function with name `ä` wasn't defined\n", err);
+}
+
+#[test]
+fn repl_loads_file() {
+    assert_eq!(
+        (
+            "🆘> false\n🆘> ö ¤ {\\. + \\:) * \\. + .:::\n🆘> ".into(),
+            "".into()
+        ),
+        repl(":load example.🆘\n:defs\n")
+    );
+}
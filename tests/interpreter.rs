@@ -1,29 +1,109 @@
-use sos::interpreter::{self, State, Value};
-use sos::parser;
+use sos::interpreter::{
+    self, Buffer, Closure, ErrorKind, Limit, Limits, Overflow, RuntimeError, State, Value,
+};
+use sos::parser::{self, Expr, ExprKind, Ident, Param, Span};
+use sos::vm;
+
+use std::io::Cursor;
+
+type Backend = fn(&mut State, &[Expr]) -> interpreter::Result<Value>;
+
+const BACKENDS: &[(&str, Backend)] = &[("interpreter", interpreter::interpret), ("vm", vm::run)];
+
+/// Runs program with every backend on fresh state and checks that they agree on the result and
+/// the output.
+fn run_ast(
+    ast: &[Expr],
+    input: &str,
+    setup: impl Fn(&mut State),
+) -> (interpreter::Result<Value>, String) {
+    let mut results = BACKENDS.iter().map(|(name, backend)| {
+        let buffer = Buffer::new();
+        let mut state = State::with_io(Cursor::new(input.to_owned()), buffer.clone());
+        setup(&mut state);
+        (name, backend(&mut state, ast), buffer.contents())
+    });
+    let (_, expected, expected_output) = results.next().unwrap();
+    for (name, res, output) in results {
+        assert_eq!(expected_output, output, "Output of {} differs", name);
+        match (&expected, &res) {
+            (Ok(expected), Ok(res)) => assert_eq!(expected, res, "Value of {} differs", name),
+            (Err(expected), Err(res)) => {
+                assert_eq!(
+                    expected.to_string(),
+                    res.to_string(),
+                    "Error of {} differs",
+                    name
+                );
+                assert_eq!(
+                    expected.expr.as_ref().map(|e| (e, e.span)),
+                    res.expr.as_ref().map(|e| (e, e.span)),
+                    "Error location of {} differs",
+                    name
+                );
+            }
+            _ => panic!("Result of {} differs: {:?} vs {:?}", name, expected, res),
+        }
+    }
+    (expected, expected_output)
+}
+
+fn run(
+    code: &str,
+    input: &str,
+    setup: impl Fn(&mut State),
+) -> (interpreter::Result<Value>, String) {
+    let ast = parser::parse(&parser::State::default(), code).expect("Parsing failed");
+    run_ast(&ast, input, setup)
+}
+
+fn vector(components: &[i64]) -> Value {
+    Value::Vector(components.iter().map(|&c| c.into()).collect())
+}
 
 fn interpret_expr(code: &str) -> Value {
     let res = parser::expr(&parser::State::default())(code)
         .expect("Parsing failed")
         .1;
-    interpreter::interpret_expr(&mut State::new(), &res)
+    run_ast(&[res], "", |_| {}).0.expect("Evaluation failed")
+}
+
+fn interpret(code: &str) -> Value {
+    run(code, "", |_| {}).0.expect("Evaluation failed")
+}
+
+fn output(code: &str) -> String {
+    output_with(code, "", |_| {})
+}
+
+fn output_with(code: &str, input: &str, setup: impl Fn(&mut State)) -> String {
+    let (res, output) = run(code, input, setup);
+    res.expect("Evaluation failed");
+    output
+}
+
+fn error(code: &str) -> RuntimeError {
+    run(code, "a 1", |_| {})
+        .0
+        .expect_err("Evaluation succeeded")
 }
 
 #[test]
 fn interpret_numbers() {
-    assert_eq!(Value::Vector(vec![1]), interpret_expr("."));
-    assert_eq!(Value::Vector(vec![2]), interpret_expr(":"));
-    assert_eq!(Value::Vector(vec![3]), interpret_expr(".:"));
-    assert_eq!(Value::Vector(vec![4]), interpret_expr("::"));
-    assert_eq!(Value::Vector(vec![5]), interpret_expr(".::"));
-    assert_eq!(Value::Vector(vec![6]), interpret_expr(":::"));
-    assert_eq!(Value::Vector(vec![7]), interpret_expr(".:::"));
+    assert_eq!(vector(&[1]), interpret_expr("."));
+    assert_eq!(vector(&[2]), interpret_expr(":"));
+    assert_eq!(vector(&[3]), interpret_expr(".:"));
+    assert_eq!(vector(&[4]), interpret_expr("::"));
+    assert_eq!(vector(&[5]), interpret_expr(".::"));
+    assert_eq!(vector(&[6]), interpret_expr(":::"));
+    assert_eq!(vector(&[7]), interpret_expr(".:::"));
 }
 
 #[test]
 fn interpret_vectors() {
-    assert_eq!(Value::Vector(vec![1, 2]), interpret_expr(". :"));
-    assert_eq!(Value::Vector(vec![1, 2, 3]), interpret_expr(". : .:"));
-    assert_eq!(Value::Vector(vec![1, 2, 3, 4]), interpret_expr(". : .: ::"));
+    assert_eq!(vector(&[1, 2]), interpret_expr(". :"));
+    assert_eq!(vector(&[1, 2, 3]), interpret_expr(". : .:"));
+    assert_eq!(vector(&[1, 2, 3, 4]), interpret_expr(". : .: ::"));
 }
 
 #[test]
@@ -72,10 +152,632 @@ fn interpret_equality_of_functions() {
     );
 }
 
+#[test]
+fn interpret_equality_of_renamed_functions() {
+    assert_eq!(
+        Value::Boolean(true),
+        interpret_expr(r"{ö ¤ ä ¤ {ä) + \.) = {ö ¤ å ¤ {å) + \.)")
+    );
+    assert_eq!(
+        Value::Boolean(false),
+        interpret_expr(r"{ö ¤ {ä ¤ .) + å ¤ ä) = {ö ¤ {ä ¤ .) + å ¤ å)")
+    );
+    assert_eq!(Value::Boolean(false), interpret_expr(r"{ö ¤ ä) = {ö ¤ å)"));
+    assert_eq!(
+        Value::Boolean(false),
+        interpret_expr(r"{ö ¤ å ¤ \.) = {ä ¤ 🆘 ¤ \:)")
+    );
+    assert_eq!(
+        Value::Boolean(false),
+        interpret("ä ¤ /x\nå ¤ /y\n{ö ¤ {ä ¤ .) + ä) = {ü ¤ {å ¤ .) + å)")
+    );
+    assert_eq!(
+        Value::Boolean(true),
+        interpret_expr(r"{ö ¤ {ä ¤ .) + {ä ¤ :)) = {ü ¤ {å ¤ .) + {ë ¤ :))")
+    );
+}
+
 #[test]
 fn interpret_addition_of_numbers() {
-    assert_eq!(Value::Vector(vec![2]), interpret_expr(r#". + ."#));
-    assert_eq!(Value::Vector(vec![3]), interpret_expr(r#". + :"#));
-    assert_eq!(Value::Vector(vec![3]), interpret_expr(r#": + ."#));
-    assert_eq!(Value::Vector(vec![4]), interpret_expr(r#": + :"#));
+    assert_eq!(vector(&[2]), interpret_expr(r#". + ."#));
+    assert_eq!(vector(&[3]), interpret_expr(r#". + :"#));
+    assert_eq!(vector(&[3]), interpret_expr(r#": + ."#));
+    assert_eq!(vector(&[4]), interpret_expr(r#": + :"#));
+}
+
+#[test]
+fn interpret_addition_of_vectors() {
+    assert_eq!(vector(&[2, 3, 3]), interpret_expr("{. :) + . . .:"));
+    assert_eq!(vector(&[]), interpret_expr("{. :) + {@ << .) - . :"));
+}
+
+#[test]
+fn interpret_addition_of_vector_and_boolean() {
+    assert_eq!(vector(&[1, 2]), interpret_expr("{. :) + {. = .)"));
+    assert_eq!(vector(&[1]), interpret_expr(". + {. = :)"));
+}
+
+#[test]
+fn interpret_addition_of_text_and_boolean() {
+    assert_eq!(Value::Text("cba".into()), interpret_expr("{. = .) + /abc"));
+    assert_eq!(Value::Text("abc".into()), interpret_expr("{. = :) + /abc"));
+    assert_eq!(Value::Text("abc".into()), interpret_expr("/abc + {. = .)"));
+    assert_eq!(Value::Text("cba".into()), interpret_expr("/abc + {. = :)"));
+}
+
+#[test]
+fn interpret_addition_of_texts_and_vectors() {
+    assert_eq!(Value::Text("a b".into()), interpret_expr("/a + /b"));
+    assert_eq!(Value::Text("a12".into()), interpret_expr("/a + . :"));
+    assert_eq!(Value::Text("12a".into()), interpret_expr("{. :) + /a"));
+}
+
+#[test]
+fn interpret_seeded_shuffles_are_reproducible() {
+    let code = "ö ¤ {. = .) + . : .: :: .:: ::: .::: :::: .:::: :::::
+        @ << ö
+        @ << {ö)
+        @ << {. = .) + ö
+        @ << ö
+        . :";
+    let first = output_with(code, "", |state| state.seed(7));
+    assert_eq!(first, output_with(code, "", |state| state.seed(7)));
+    assert_eq!(4, first.lines().count());
+}
+
+#[test]
+fn interpret_addition_of_functions_and_empty() {
+    assert_eq!(vector(&[3]), interpret_expr("{ö ¤ :) + ."));
+    assert_eq!(Value::Text("a".into()), interpret_expr("{@ << .) + ö ¤ /a"));
+    assert_eq!(Value::Text("a".into()), interpret_expr("/a + @ << ."));
+}
+
+#[test]
+fn interpret_closures_capture_functions() {
+    assert_eq!("Ok out\n", output("@ << {given that å ¤ /Ok ä ¤ å) + /out"));
+}
+
+#[test]
+fn interpret_calls_in_env_of_definition() {
+    assert_eq!(
+        "global\n",
+        output("å ¤ /global\nä ¤ å\nö ¤ given that å ¤ /Ok ä\n@ << ö")
+    );
+}
+
+#[test]
+fn interpret_closures_capture_params() {
+    assert_eq!("5\n", output("@ << {! {¤ ä ¤ \\. + :) {:)) + ."));
+    assert_eq!("5\n", output("ö ¤ {ä ¤ \\. + :) + .\n@ << ö {:)"));
+}
+
+#[test]
+fn interpret_params_of_current_call() {
+    assert_eq!("3\n", output("ö ¤ \\. + \\:\n@ << ö {.) {:)"));
+    assert_eq!("3\n", output("ä ¤ \\:\nö ¤ ä {\\.) {\\.)\n@ << ö {.:) {:)"));
+    let e = error("ä ¤ \\:\nö ¤ ä {\\:)\nö {.) {:)");
+    assert_eq!("Unbound param `2`", e.to_string());
+}
+
+#[test]
+fn interpret_numbers_as_separate_arguments() {
+    assert_eq!("3\n", output("ö ¤ \\. + \\:\n@ << ö . :"));
+    assert_eq!("false\n", output(include_str!("../example.🆘")));
+}
+
+#[test]
+fn interpret_arities_of_shadowed_functions() {
+    assert_eq!("1 2\n", output("ö ¤ \\\\\n{ö ¤ .)\n@ << ö {.) {:)"));
+}
+
+#[test]
+fn interpret_all_params_as_vector() {
+    assert_eq!("1 2 3 2\n", output("ö ¤ \\\\\n@ << ö {.) {: .:) {/ab )"));
+    assert_eq!("7 1 2 4\n", output("ö ¤ .::: \\\\ ::\n@ << ö {.) {:)"));
+    assert_eq!(
+        "a\nb\n0 0\n",
+        output("ö ¤ \\\\\n@ << ö {¤ @ << /a ) {¤ @ << /b )")
+    );
+    assert_eq!("\n", output("ö ¤ \\\\\n@ << ö"));
+    assert_eq!("Unbound param `0`", error("\\\\").to_string());
+}
+
+#[test]
+fn interpret_lambdas() {
+    assert_eq!("hi\n", output("@ << ! {¤ /hi )"));
+    assert_eq!("4\n", output("@ << ! {¤ \\\\ + \\\\) {:)"));
+    assert_eq!("hi there\n", output("ä ¤ ¤ /hi\n@ << {ä) + /there"));
+    assert_eq!("yes\n", output("ö ¤ ! \\. {:)\n@ << ö {¤ /yes )"));
+}
+
+#[test]
+fn interpret_equality_of_lambdas() {
+    assert_eq!(
+        Value::Boolean(true),
+        interpret_expr(r"{¤ \. + :) = {¤ \. + :)")
+    );
+    assert_eq!(Value::Boolean(false), interpret_expr(r"{¤ .) = {¤ :)"));
+}
+
+#[test]
+fn interpret_lambdas_are_not_defined() {
+    let res = parser::parse(&parser::State::default(), "¤ /x").expect("Parsing failed");
+    for (_, backend) in BACKENDS {
+        let mut state = State::with_output(Buffer::new());
+        backend(&mut state, &res).expect("Evaluation failed");
+        assert!(state.definitions().is_empty());
+    }
+}
+
+#[test]
+fn interpret_applying_non_function() {
+    let e = error("! {.) :");
+    assert!(matches!(e.kind, ErrorKind::NotAFunction(_)));
+    assert_eq!("Can't apply `1` which isn't a function", e.to_string());
+}
+
+#[test]
+fn interpret_tail_calls_in_constant_stack() {
+    let code = "ö ¤ given that @ >> {ö) otherwise /done\n@ << ö";
+    let input = "Ok\n".repeat(1_000_000);
+    assert_eq!("done\n", output_with(code, &input, |_| {}));
+    let code = "ö ¤ given that @ >> ! {¤ ö) otherwise /done\n@ << ö";
+    let input = "Ok\n".repeat(100_000);
+    assert_eq!("done\n", output_with(code, &input, |_| {}));
+}
+
+#[test]
+fn interpret_definitions_in_calls_stay_local() {
+    let e = error("ö ¤ {ä ¤ .)\nö\nä");
+    assert!(matches!(e.kind, ErrorKind::UndefinedFunction(Ident(ref n, _)) if n == "ä"));
+}
+
+fn samples() -> Vec<Value> {
+    let function = |code| {
+        Value::Function(Closure::new(
+            parser::expr(&parser::State::default())(code)
+                .expect("Parsing failed")
+                .1,
+        ))
+    };
+    vec![
+        Value::Text("".into()),
+        Value::Text("a".into()),
+        Value::Text("🆘 Ok".into()),
+        Value::Boolean(true),
+        Value::Boolean(false),
+        vector(&[]),
+        vector(&[0]),
+        vector(&[1, -2, 3]),
+        vector(&[-7, 0, 0]),
+        function("/f"),
+        function(". :"),
+        function(". = ."),
+        function("@ << ."),
+        function("ö ¤ ."),
+        Value::Empty,
+    ]
+}
+
+type Operation = fn(&mut State, Value, Value) -> interpreter::Result<Value>;
+
+#[test]
+fn interpret_operations_on_all_pairs_of_values() {
+    let operations: &[(&str, Operation)] = &[
+        ("+", interpreter::interpret_addition),
+        ("-", interpreter::interpret_subtraction),
+        ("*", interpreter::interpret_multiplication),
+    ];
+    let mut state = State::with_io("".as_bytes(), Buffer::new());
+    for (name, operation) in operations {
+        for lhs in samples() {
+            for rhs in samples() {
+                let res = operation(&mut state, lhs.clone(), rhs.clone());
+                assert!(
+                    res.is_ok(),
+                    "`{:?} {} {:?}` failed: {:?}",
+                    lhs,
+                    name,
+                    rhs,
+                    res
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn interpret_multiplication_of_numbers() {
+    assert_eq!(vector(&[1]), interpret_expr(". * ."));
+    assert_eq!(vector(&[6]), interpret_expr(": * .:"));
+}
+
+#[test]
+fn interpret_multiplication_of_vectors() {
+    assert_eq!(vector(&[2, 4, 6]), interpret_expr(": * . : .:"));
+    assert_eq!(vector(&[2, 4, 6]), interpret_expr("{. : .:) * :"));
+    assert_eq!(vector(&[11]), interpret_expr("{. :) * .: ::"));
+    assert_eq!(vector(&[3, 6, 9]), interpret_expr("{. : .:) * .:"));
+    assert_eq!(vector(&[5]), interpret_expr("{. :) * .: . ."));
+}
+
+#[test]
+fn interpret_multiplication_of_booleans() {
+    assert_eq!(Value::Boolean(true), interpret_expr("{. = .) * {. = .)"));
+    assert_eq!(Value::Boolean(false), interpret_expr("{. = .) * {. = :)"));
+    assert_eq!(Value::Boolean(false), interpret_expr("{. = :) * {. = .)"));
+    assert_eq!(Value::Boolean(false), interpret_expr("{. = :) * {. = :)"));
+}
+
+#[test]
+fn interpret_multiplication_of_boolean_and_text() {
+    assert_eq!(Value::Text("ab".into()), interpret_expr("{. = .) * /ab"));
+    assert_eq!(Value::Text("".into()), interpret_expr("{. = :) * /ab"));
+    assert_eq!(Value::Text("ab".into()), interpret_expr("/ab * {. = .)"));
+    assert_eq!(Value::Text("".into()), interpret_expr("/ab * {. = :)"));
+}
+
+#[test]
+fn interpret_multiplication_of_boolean_and_vector() {
+    assert_eq!(vector(&[1, 2]), interpret_expr("{. = .) * . :"));
+    assert_eq!(vector(&[]), interpret_expr("{. = :) * . :"));
+    assert_eq!(vector(&[1, 2]), interpret_expr("{. :) * {. = .)"));
+    assert_eq!(vector(&[]), interpret_expr("{. :) * {. = :)"));
+}
+
+#[test]
+fn interpret_multiplication_of_texts() {
+    assert_eq!(Value::Text("bcb".into()), interpret_expr("/abcb * /cbd"));
+    assert_eq!(Value::Text("".into()), interpret_expr("/abc * /def"));
+}
+
+#[test]
+fn interpret_multiplication_of_text_and_vector() {
+    assert_eq!(Value::Text("ababab".into()), interpret_expr("/ab * .:"));
+    assert_eq!(Value::Text("ababab".into()), interpret_expr("/ab * . :"));
+    assert_eq!(Value::Text("abab".into()), interpret_expr(": * /ab"));
+    assert_eq!("baba\n", output_with("@ << /ab * @ >> #", "-2", |_| {}));
+    assert_eq!("\n", output_with("@ << /ab * @ >> #", "2 -2", |_| {}));
+}
+
+#[test]
+fn interpret_multiplication_of_functions() {
+    assert_eq!(vector(&[6]), interpret_expr("{ö ¤ :) * .:"));
+    assert_eq!(Value::Text("aa".into()), interpret_expr("/a * ö ¤ :"));
+}
+
+#[test]
+fn interpret_multiplication_of_empty() {
+    assert_eq!(Value::Empty, interpret_expr("{@ << .) * ."));
+    assert_eq!(Value::Empty, interpret_expr("/a * @ << ."));
+    assert_eq!(Value::Empty, interpret_expr("{. = .) * @ << ."));
+}
+
+#[test]
+fn interpret_subtraction_of_numbers() {
+    assert_eq!(vector(&[1]), interpret_expr(": - ."));
+    assert_eq!(vector(&[-1]), interpret_expr(". - :"));
+    assert_eq!(vector(&[]), interpret_expr(".: - .:"));
+}
+
+#[test]
+fn interpret_subtraction_of_vectors() {
+    assert_eq!(vector(&[0, 1]), interpret_expr("{. .:) - . :"));
+    assert_eq!(vector(&[1]), interpret_expr("{: :) - . :"));
+    assert_eq!(vector(&[1, -2, -3]), interpret_expr(": - . : .:"));
+}
+
+#[test]
+fn interpret_subtraction_of_booleans() {
+    assert_eq!(Value::Boolean(false), interpret_expr("{. = .) - {. = .)"));
+    assert_eq!(Value::Boolean(true), interpret_expr("{. = .) - {. = :)"));
+    assert_eq!(Value::Boolean(true), interpret_expr("{. = :) - {. = .)"));
+    assert_eq!(Value::Boolean(false), interpret_expr("{. = :) - {. = :)"));
+}
+
+#[test]
+fn interpret_subtraction_of_boolean_and_text() {
+    assert_eq!(Value::Text("abc".into()), interpret_expr("{. = .) - /cab"));
+    assert_eq!(Value::Text("cab".into()), interpret_expr("{. = :) - /cab"));
+    assert_eq!(Value::Text("cab".into()), interpret_expr("/cab - {. = .)"));
+    assert_eq!(Value::Text("abc".into()), interpret_expr("/cab - {. = :)"));
+}
+
+#[test]
+fn interpret_subtraction_of_boolean_and_vector() {
+    assert_eq!(vector(&[1, 2, 3]), interpret_expr("{. = .) - : .: ."));
+    assert_eq!(vector(&[2, 3, 1]), interpret_expr("{. = :) - : .: ."));
+    assert_eq!(vector(&[2, 3, 1]), interpret_expr("{: .: .) - {. = .)"));
+    assert_eq!(vector(&[1, 2, 3]), interpret_expr("{: .: .) - {. = :)"));
+}
+
+#[test]
+fn interpret_subtraction_of_texts() {
+    assert_eq!(Value::Text("ac".into()), interpret_expr("/abbc - /b"));
+    assert_eq!(Value::Text("a d".into()), interpret_expr("/abc/ d - /bc"));
+    assert_eq!(Value::Text("abc".into()), interpret_expr("/abc - /d"));
+}
+
+#[test]
+fn interpret_subtraction_of_text_and_vector() {
+    assert_eq!(Value::Text("a".into()), interpret_expr("/abc - :"));
+    assert_eq!(Value::Text("".into()), interpret_expr("/abc - ::"));
+    assert_eq!("bc\n", output_with("@ << /abc - @ >> #", "-1", |_| {}));
+    assert_eq!(Value::Text("c".into()), interpret_expr(": - /abc"));
+    assert_eq!(Value::Text("".into()), interpret_expr(":: - /abc"));
+}
+
+#[test]
+fn interpret_subtraction_of_functions() {
+    assert_eq!(vector(&[-1]), interpret_expr("{ö ¤ :) - .:"));
+    assert_eq!(Value::Text("a".into()), interpret_expr("/ab - ö ¤ ."));
+}
+
+#[test]
+fn interpret_subtraction_of_empty() {
+    assert_eq!(vector(&[1]), interpret_expr(". - @ << ."));
+    assert_eq!(vector(&[-1, -2]), interpret_expr("{@ << .) - . :"));
+    assert_eq!(Value::Boolean(true), interpret_expr("{@ << .) - {. = .)"));
+    assert_eq!(Value::Empty, interpret_expr("{@ << .) - /a"));
+    assert_eq!(Value::Empty, interpret_expr("{@ << .) - @ << ."));
+}
+
+#[test]
+fn interpret_empty_program() {
+    assert_eq!(Value::Empty, interpret(""));
+}
+
+#[test]
+fn interpret_program_returns_last_value() {
+    assert_eq!(vector(&[2]), interpret(".\n:"));
+    assert_eq!(Value::Boolean(true), interpret(". = :\n: = :"));
+}
+
+#[test]
+fn interpret_program_with_top_level_definition() {
+    assert_eq!(Value::Boolean(true), interpret("ö ¤ :\nö = :"));
+    assert_eq!(Value::Boolean(false), interpret("ö ¤ :\nä ¤ .\nö = ä"));
+}
+
+#[test]
+fn interpret_writing_values() {
+    assert_eq!("true\n", output("@ << /true"));
+    assert_eq!("Hello, World!\n", output("@ << /Hello,/ World!"));
+    assert_eq!("true\n", output("@ << : = :"));
+    assert_eq!("1 2 3\n", output("@ << . : .:"));
+    assert_eq!("¤\n", output("@ << ö ¤ ."));
+}
+
+#[test]
+fn interpret_writing_empty() {
+    assert_eq!("1\n\n", output("@ << {@ << .)"));
+}
+
+#[test]
+fn interpret_reading_lines() {
+    assert_eq!(
+        "first\nsecond\n",
+        output_with("@ << @ >>\n@ << @ >>", "first\r\nsecond", |_| {})
+    );
+    assert_eq!("\n", output_with("@ << @ >>", "", |_| {}));
+}
+
+#[test]
+fn interpret_reading_all() {
+    assert_eq!("a\nb\n\n", output_with("@ << @ >>>", "a\nb\n", |_| {}));
+    assert_eq!("b\n\n", output_with("@ >>\n@ << @ >>>", "a\nb\n", |_| {}));
+}
+
+#[test]
+fn interpret_numbers_beyond_machine_integers() {
+    let max = "9223372036854775807\n9223372036854775807\n";
+    assert_eq!(
+        "9223372036854775808\n",
+        output_with("@ << {@ >> #) + .", max, |_| {})
+    );
+    assert_eq!(
+        "-9223372036854775809\n",
+        output_with("@ << {@ >> #) - :", "-9223372036854775807", |_| {})
+    );
+    assert_eq!(
+        "85070591730234615847396907784232501249\n",
+        output_with("@ << {@ >> #) * {@ >> #)", max, |_| {})
+    );
+    assert_eq!(
+        "true\n",
+        output_with("@ << {@ >> # + .) = {@ >> # + .)", max, |_| {})
+    );
+    assert_eq!(
+        "a18446744073709551616\n",
+        output_with("@ << /a + {{@ >> #) + {@ >> #) + :)", max, |_| {})
+    );
+}
+
+#[test]
+fn interpret_wrapping_arithmetic() {
+    let wrapping = |state: &mut State| state.set_overflow(Overflow::Wrapping);
+    let max = "9223372036854775807\n9223372036854775807\n";
+    assert_eq!(
+        "-9223372036854775808\n",
+        output_with("@ << {@ >> #) + .", max, wrapping)
+    );
+    assert_eq!(
+        "9223372036854775807\n",
+        output_with("@ << {@ >> #) - .", "-9223372036854775808", wrapping)
+    );
+    assert_eq!(
+        "1\n",
+        output_with("@ << {@ >> #) * {@ >> #)", max, wrapping)
+    );
+    assert_eq!(
+        "x\n-9223372036854775808\n",
+        output_with(
+            "@ << {@ << /x ) - {@ >> #)",
+            "-9223372036854775808",
+            wrapping
+        )
+    );
+    assert_eq!(
+        "-2\n",
+        output_with("@ << @ >> #", "18446744073709551614", wrapping)
+    );
+}
+
+#[test]
+fn interpret_overflowing_text_repetition() {
+    let e = run("@ << /ab * @ >> #", "9223372036854775807", |_| {})
+        .0
+        .expect_err("Evaluation succeeded");
+    assert!(matches!(e.kind, ErrorKind::Overflow("text repetition")));
+    assert_eq!("Overflow in text repetition", e.to_string());
+}
+
+#[test]
+fn interpret_truthiness_of_huge_numbers() {
+    let code = "@ << given that @ >> # /yes otherwise /no";
+    assert_eq!(
+        "no\n",
+        output_with(code, "1180591620717411303429 3", |_| {})
+    );
+    assert_eq!(
+        "no\n",
+        output_with(code, "3 -1180591620717411303429 7", |_| {})
+    );
+}
+
+#[test]
+fn interpret_reading_numbers() {
+    assert_eq!(
+        "1 -2 30\n",
+        output_with("@ << @ >> #", " 1 -2  30\n4", |_| {})
+    );
+    assert_eq!("true\n", output_with("@ << @ >> # = . :", "1 2", |_| {}));
+}
+
+#[test]
+fn interpret_undefined_function() {
+    let e = error("ö . + .");
+    assert!(matches!(e.kind, ErrorKind::UndefinedFunction(Ident(ref n, _)) if n == "ö"));
+    assert!(matches!(e.expr.map(|e| e.kind), Some(ExprKind::Call(..))));
+}
+
+#[test]
+fn interpret_unbound_param() {
+    let e = error(r"@ << \:");
+    assert!(matches!(e.kind, ErrorKind::UnboundParam(2)));
+    assert_eq!(
+        Some(ExprKind::Param(Param(2, Span::default()))),
+        e.expr.map(|e| e.kind)
+    );
+}
+
+#[test]
+fn interpret_error_location() {
+    let e = error("/a\n@ << {\\:)");
+    let span = e.expr.expect("Missing expression").span;
+    assert_eq!((2, 7, 11), (span.line, span.column, span.end));
+}
+
+#[test]
+fn interpret_invalid_number_input() {
+    let e = error("@ >> #");
+    assert!(matches!(e.kind, ErrorKind::InvalidNumber(ref n) if n == "a"));
+}
+
+#[test]
+fn interpret_error_message() {
+    assert_eq!(
+        "Function with name `ö` wasn't defined",
+        error("{ö)").to_string()
+    );
+}
+
+#[test]
+fn interpret_step_limit() {
+    let limits = Limits {
+        steps: Some(100),
+        ..Limits::default()
+    };
+    let (res, output) = run("ö ¤ {@ << /tick ) + {ö)\nö", "", |state| {
+        state.set_limits(limits)
+    });
+    let e = res.expect_err("Evaluation succeeded");
+    assert!(matches!(
+        e.kind,
+        ErrorKind::LimitExceeded(Limit::Steps(100))
+    ));
+    assert_eq!("Exceeded the limit of 100 steps", e.to_string());
+    assert_eq!(16, output.lines().count());
+    let (res, _) = run("@ << . + .", "", |state| state.set_limits(limits));
+    assert!(res.is_ok());
+}
+
+#[test]
+fn interpret_call_depth_limit() {
+    let limits = Limits {
+        call_depth: Some(50),
+        ..Limits::default()
+    };
+    let (res, _) = run("ö ¤ {ö) + .\nö", "", |state| state.set_limits(limits));
+    let e = res.expect_err("Evaluation succeeded");
+    assert!(matches!(
+        e.kind,
+        ErrorKind::LimitExceeded(Limit::CallDepth(50))
+    ));
+    assert_eq!("Exceeded the limit of 50 nested calls", e.to_string());
+    let code = "ö ¤ given that @ >> {ö) otherwise /done\n@ << ö";
+    let input = "Ok\n".repeat(100);
+    let (res, output) = run(code, &input, |state| state.set_limits(limits));
+    res.expect("Evaluation failed");
+    assert_eq!("done\n", output);
+}
+
+#[test]
+fn interpret_size_limits() {
+    let limits = Limits {
+        vector_length: Some(3),
+        text_size: Some(100),
+        ..Limits::default()
+    };
+    let (res, _) = run("@ << . : .:\n@ << . : .: ::", "", |state| {
+        state.set_limits(limits)
+    });
+    let e = res.expect_err("Evaluation succeeded");
+    assert!(matches!(
+        e.kind,
+        ErrorKind::LimitExceeded(Limit::VectorLength(3))
+    ));
+    let (res, output) = run(
+        "@ << /ab * : \n@ << /ab * @ >> #",
+        "1000000000000",
+        |state| state.set_limits(limits),
+    );
+    let e = res.expect_err("Evaluation succeeded");
+    assert!(matches!(
+        e.kind,
+        ErrorKind::LimitExceeded(Limit::TextSize(100))
+    ));
+    assert_eq!("Exceeded the limit of 100 bytes of text", e.to_string());
+    assert_eq!("abab\n", output);
+}
+
+#[test]
+fn interpret_vector_bits_limit() {
+    let limits = Limits {
+        vector_bits: Some(64),
+        ..Limits::default()
+    };
+    let (res, output) = run("@ << . :\nö ¤ ö {\\. * \\.)\nö :", "", |state| {
+        state.set_limits(limits)
+    });
+    let e = res.expect_err("Evaluation succeeded");
+    assert!(matches!(
+        e.kind,
+        ErrorKind::LimitExceeded(Limit::VectorBits(64))
+    ));
+    assert_eq!(
+        "Exceeded the limit of 64 bits of vector components",
+        e.to_string()
+    );
+    assert_eq!("1 2\n", output);
 }
@@ -1,19 +1,42 @@
-use sos::parser::{parse, Expr as E, State, Ident, Op, VectorComponent, Param};
+use sos::parser::{parse, Expr, ExprKind as E, State, Ident, Op, VectorComponent, Param, ReadMode, ParseError, Reason, Span};
 
-fn b<T>(t: T) -> Box<T> {
-    Box::new(t)
+// Nodes compare equal regardless of their spans, so expectations are built with default spans.
+
+fn b(e: E) -> Box<Expr> {
+    Box::new(e.into())
+}
+
+fn failure(e: Option<E>) -> Box<Option<Expr>> {
+    Box::new(e.map(Expr::from))
+}
+
+fn r(exprs: Vec<E>) -> Result<Vec<Expr>, ParseError> {
+    Ok(exprs.into_iter().map(Expr::from).collect())
+}
+
+fn id(name: &str) -> Ident {
+    Ident(name.into(), Span::default())
 }
 
-fn r<'a, T>(t: T) -> nom::IResult<&'a str, T> {
-    Ok(("", t))
+fn component(n: u64) -> VectorComponent {
+    VectorComponent::Number(n, Span::default())
+}
+
+fn call(name: Ident, params: Vec<E>) -> E {
+    E::Call(name, params.into_iter().map(Expr::from).collect())
+}
+
+fn err(code: &str) -> (Reason, usize, usize, String) {
+    let e = parse(&State::default(), code).expect_err("Parsing succeeded");
+    (e.reason, e.line, e.column, e.snippet)
 }
 
 fn number(n: u64) -> E {
-    E::Vector(vec![VectorComponent::Number(n)])
+    E::Vector(vec![component(n)])
 }
 
 fn param(n: u64) -> E {
-    E::Param(Param(n))
+    E::Param(Param(n, Span::default()))
 }
 
 fn scope(e: E) -> E {
@@ -34,19 +57,19 @@ fn parse_numbers() {
 #[test]
 fn parse_vectors() {
     assert_eq!(r(vec![E::Vector(vec![
-        VectorComponent::Number(1),
-        VectorComponent::Number(2),
+        component(1),
+        component(2),
     ])]), parse(&State::default(), ". :"));
     assert_eq!(r(vec![E::Vector(vec![
-        VectorComponent::Number(1),
-        VectorComponent::Number(2),
-        VectorComponent::Number(3),
+        component(1),
+        component(2),
+        component(3),
     ])]), parse(&State::default(), ". : .:"));
     assert_eq!(r(vec![E::Vector(vec![
-        VectorComponent::Number(1),
-        VectorComponent::Number(2),
-        VectorComponent::Number(3),
-        VectorComponent::Number(4),
+        component(1),
+        component(2),
+        component(3),
+        component(4),
     ])]), parse(&State::default(), ". : .: ::"));
 }
 
@@ -55,9 +78,11 @@ fn parse_vectors() {
 fn parse_conditional() {
     assert_eq!(
         r(vec![E::Conditional {
+            phrase: 0,
             condition: b(number(1)),
             success: b(scope(number(1))),
-            failure: b(None),
+            else_phrase: 0,
+            failure: failure(None),
         }]),
         parse(&State::default(), "given that.{.")
     );
@@ -67,9 +92,11 @@ fn parse_conditional() {
 fn parse_conditional_whitespace() {
     assert_eq!(
         r(vec![E::Conditional {
+            phrase: 0,
             condition: b(number(1)),
             success: b(scope(number(1))),
-            failure: b(None),
+            else_phrase: 0,
+            failure: failure(None),
         }]),
         parse(&State::default(), "given that . { . ")
     );
@@ -79,9 +106,11 @@ fn parse_conditional_whitespace() {
 fn parse_conditional_with_else() {
     assert_eq!(
         r(vec![E::Conditional {
+            phrase: 0,
             condition: b(number(1)),
             success: b(scope(number(1))),
-            failure: b(Some(number(1))),
+            else_phrase: 0,
+            failure: failure(Some(number(1))),
         }]),
         parse(&State::default(), "given that.{.)otherwise.")
     );
@@ -91,9 +120,11 @@ fn parse_conditional_with_else() {
 fn parse_conditional_with_else_whitespace() {
     assert_eq!(
         r(vec![E::Conditional {
+            phrase: 0,
             condition: b(number(1)),
             success: b(scope(number(1))),
-            failure: b(Some(number(1))),
+            else_phrase: 0,
+            failure: failure(Some(number(1))),
         }]),
         parse(&State::default(), "given that . { . ) otherwise . ")
     );
@@ -102,7 +133,7 @@ fn parse_conditional_with_else_whitespace() {
 #[test]
 fn parse_function_definition() {
     assert_eq!(
-        r(vec![E::Definition(Ident("öäå".into()), b(number(1)))]),
+        r(vec![E::Definition(id("öäå"), b(number(1)))]),
         parse(&State::default(), "öäå¤.")
     );
 }
@@ -110,7 +141,7 @@ fn parse_function_definition() {
 #[test]
 fn parse_function_definition_in_scope() {
     assert_eq!(
-        r(vec![E::Scope(Box::new(E::Definition(Ident("ö".into()), b(number(1)))))]),
+        r(vec![E::Scope(b(E::Definition(id("ö"), b(number(1)))))]),
         parse(&State::default(), "{ö¤.)")
     );
 }
@@ -118,7 +149,7 @@ fn parse_function_definition_in_scope() {
 #[test]
 fn parse_function_definition_in_scope_whitespace() {
     assert_eq!(
-        r(vec![E::Scope(Box::new(E::Definition(Ident("ö".into()), b(number(1)))))]),
+        r(vec![E::Scope(b(E::Definition(id("ö"), b(number(1)))))]),
         parse(&State::default(), "{ ö ¤ . )")
     );
 }
@@ -127,9 +158,9 @@ fn parse_function_definition_in_scope_whitespace() {
 fn parse_equality_of_two_function_definitions() {
     assert_eq!(
         r(vec![E::Op(
-            b(E::Scope(Box::new(E::Definition(Ident("ö".into()), b(number(1)))))),
+            b(E::Scope(b(E::Definition(id("ö"), b(number(1)))))),
             Op::Equ,
-            b(E::Scope(Box::new(E::Definition(Ident("ö".into()), b(number(1)))))),
+            b(E::Scope(b(E::Definition(id("ö"), b(number(1)))))),
         )]),
         parse(&State::default(), "{ö¤.)={ö¤.)")
     );
@@ -139,7 +170,7 @@ fn parse_equality_of_two_function_definitions() {
 #[test]
 fn parse_function_definition_whitespace() {
     assert_eq!(
-        r(vec![E::Definition(Ident("öäå".into()), b(number(1)))]),
+        r(vec![E::Definition(id("öäå"), b(number(1)))]),
         parse(&State::default(), "öäå ¤ .")
     );
 }
@@ -147,7 +178,7 @@ fn parse_function_definition_whitespace() {
 #[test]
 fn parse_function_definition_with_param() {
     assert_eq!(
-        r(vec![E::Definition(Ident("ö".into()), b(param(1)))]),
+        r(vec![E::Definition(id("ö"), b(param(1)))]),
         parse(&State::default(), r"ö ¤ \.")
     );
 }
@@ -155,23 +186,67 @@ fn parse_function_definition_with_param() {
 #[test]
 fn parse_function_definition_with_all_params_as_vector() {
     assert_eq!(
-        r(vec![E::Definition(Ident("ö".into()), b(param(0)))]),
+        r(vec![E::Definition(id("ö"), b(param(0)))]),
         parse(&State::default(), r"ö ¤ \\")
     );
 }
 
+#[test]
+fn parse_function_definition_with_emoji_name() {
+    assert_eq!(
+        r(vec![E::Definition(id("🆘"), b(number(1))), call(id("🆘"), vec![])]),
+        parse(&State::default(), "🆘 ¤ .\n🆘")
+    );
+}
+
 #[test]
 fn parse_multiple_function_definitions() {
     assert_eq!(
         r(vec![
-            E::Definition(Ident("ö".into()), b(param(1))),
-            E::Definition(Ident("ä".into()), b(param(1))),
+            E::Definition(id("ö"), b(param(1))),
+            E::Definition(id("ä"), b(param(1))),
         ]),
         parse(&State::default(), r"ö ¤ \.
         ä ¤ \.")
     );
 }
 
+#[test]
+fn parse_lambda() {
+    assert_eq!(
+        r(vec![E::Lambda(b(op(param(1), Op::Add, number(2))))]),
+        parse(&State::default(), r"¤ \. + :")
+    );
+}
+
+#[test]
+fn parse_lambda_as_argument() {
+    assert_eq!(
+        r(vec![call(id("ö"), vec![scope(E::Lambda(b(param(1)))), number(1)])]),
+        parse(&State::default(), r"ö {¤\.) .")
+    );
+}
+
+#[test]
+fn parse_application() {
+    assert_eq!(
+        r(vec![E::Apply(b(param(1)), vec![number(1).into(), scope(number(2)).into()])]),
+        parse(&State::default(), r"! \. . {:)")
+    );
+    assert_eq!(
+        r(vec![E::Apply(b(scope(E::Lambda(b(param(1))))), vec![])]),
+        parse(&State::default(), r"!{¤ \.)")
+    );
+}
+
+#[test]
+fn parse_error_application_without_function() {
+    assert_eq!(
+        (Reason::Expected("function to apply"), 1, 3, ".".into()),
+        err("! . :")
+    )
+}
+
 #[test]
 fn parse_preceding_whitespace() {
     assert_eq!(
@@ -246,18 +321,74 @@ fn parse_equality_whitespace() {
 }
 
 
+fn op(lhs: E, op: Op, rhs: E) -> E {
+    E::Op(b(lhs), op, b(rhs))
+}
+
+#[test]
+fn parse_multiplication_binds_tighter_than_addition() {
+    assert_eq!(
+        r(vec![op(number(1), Op::Add, op(number(2), Op::Mul, number(3)))]),
+        parse(&State::default(), ". + : * .:")
+    );
+    assert_eq!(
+        r(vec![op(op(number(1), Op::Mul, number(2)), Op::Sub, number(3))]),
+        parse(&State::default(), ". * : - .:")
+    );
+}
+
+#[test]
+fn parse_equality_binds_loosest() {
+    assert_eq!(
+        r(vec![op(
+            op(number(1), Op::Add, number(2)),
+            Op::Equ,
+            op(number(3), Op::Mul, number(4))
+        )]),
+        parse(&State::default(), ". + : = .: * ::")
+    );
+}
+
+#[test]
+fn parse_operators_are_left_associative() {
+    assert_eq!(
+        r(vec![op(op(number(1), Op::Sub, number(2)), Op::Add, number(3))]),
+        parse(&State::default(), ". - : + .:")
+    );
+    assert_eq!(
+        r(vec![op(op(number(1), Op::Mul, number(2)), Op::Mul, number(3))]),
+        parse(&State::default(), ". * : * .:")
+    );
+    assert_eq!(
+        r(vec![op(op(number(1), Op::Equ, number(2)), Op::Equ, number(3))]),
+        parse(&State::default(), ". = : = .:")
+    );
+}
+
+#[test]
+fn parse_scope_groups_operations() {
+    assert_eq!(
+        r(vec![op(scope(op(number(1), Op::Add, number(2))), Op::Mul, number(3))]),
+        parse(&State::default(), "{. + :) * .:")
+    );
+}
+
 #[test]
 fn parse_realer_function() {
     assert_eq!(
-        r(vec![E::Definition(Ident("ö".into()), b(
+        r(vec![E::Definition(id("ö"), b(
             E::Op(
-                b(scope(E::Op(
-                    b(param(1)),
-                    Op::Add,
-                    b(param(2))
-                ))), 
-                Op::Mul,
-                b(E::Op(b(param(1)), Op::Add, b(number(7))))
+                b(E::Op(
+                    b(scope(E::Op(
+                        b(param(1)),
+                        Op::Add,
+                        b(param(2))
+                    ))),
+                    Op::Mul,
+                    b(param(1))
+                )),
+                Op::Add,
+                b(number(7))
         )))]),
         parse(&State::default(), r"ö ¤ {\. + \:) * \. + .:::")
     )
@@ -271,6 +402,30 @@ fn parse_writing_io() {
     )
 }
 
+#[test]
+fn parse_reading_io() {
+    assert_eq!(
+        r(vec![E::ReadIO(ReadMode::Line)]),
+        parse(&State::default(), "@ >>")
+    );
+    assert_eq!(
+        r(vec![E::ReadIO(ReadMode::All)]),
+        parse(&State::default(), "@>>>")
+    );
+    assert_eq!(
+        r(vec![E::ReadIO(ReadMode::Numbers)]),
+        parse(&State::default(), "@ >> #")
+    );
+}
+
+#[test]
+fn parse_writing_read_io() {
+    assert_eq!(
+        r(vec![E::WriteIO(b(E::Op(b(E::ReadIO(ReadMode::Numbers)), Op::Add, b(number(1)))))]),
+        parse(&State::default(), "@ << @ >> # + .")
+    )
+}
+
 #[test]
 fn parse_simple_text() {
     assert_eq!(
@@ -354,8 +509,8 @@ fn parse_printing_text_to_io() {
 #[test]
 fn parse_call_function_in_scope() {
         assert_eq!(
-        r(vec![scope(E::Call(
-            Ident("ö".into()),
+        r(vec![scope(call(
+            id("ö"),
             vec![
                 scope(number(1)),
                 number(3)
@@ -365,14 +520,139 @@ fn parse_call_function_in_scope() {
     )
 }
 
+#[test]
+fn parse_call_takes_arguments_up_to_arity() {
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(op(param(1), Op::Add, param(2)))),
+            call(id("ö"), vec![scope(number(1)), scope(number(2))]),
+            scope(number(3)),
+        ]),
+        parse(&State::default(), r"ö ¤ \. + \:
+ö {.) {:) {.:)")
+    );
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(E::Text("a".into()))),
+            E::Op(b(call(id("ö"), vec![])), Op::Add, b(number(1))),
+        ]),
+        parse(&State::default(), "ö ¤ /a\nö + .")
+    );
+}
+
+#[test]
+fn parse_call_of_variadic_or_unknown_function_takes_all_arguments() {
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(param(0))),
+            call(id("ö"), vec![scope(number(1)), scope(number(2)), scope(number(3))]),
+        ]),
+        parse(&State::default(), r"ö ¤ \\
+ö {.) {:) {.:)")
+    );
+    assert_eq!(
+        r(vec![call(id("ä"), vec![scope(number(1)), scope(number(2))])]),
+        parse(&State::default(), "ä {.) {:)")
+    );
+}
+
+#[test]
+fn parse_arity_counts_params_of_nested_functions() {
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(E::Lambda(b(param(2))))),
+            call(id("ö"), vec![scope(number(1)), number(2)]),
+        ]),
+        parse(&State::default(), r"ö ¤ ¤ \:
+ö {.) :")
+    );
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(E::Definition(id("ä"), b(param(1))))),
+            call(id("ö"), vec![E::Text("a".into())]),
+        ]),
+        parse(&State::default(), r"ö ¤ ä ¤ \.
+ö /a")
+    );
+}
+
+#[test]
+fn parse_call_takes_numbers_as_separate_arguments() {
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(op(param(1), Op::Add, param(2)))),
+            call(id("ö"), vec![number(1), number(2)]),
+            number(3),
+        ]),
+        parse(&State::default(), r"ö ¤ \. + \:
+ö . : .:")
+    );
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(param(1))),
+            call(id("ö"), vec![scope(E::Vector(vec![component(1), component(2)]))]),
+        ]),
+        parse(&State::default(), r"ö ¤ \.
+ö {. :)")
+    );
+}
+
+#[test]
+fn parse_vectors_nested_in_arguments_take_all_components() {
+    let vector = || E::Vector(vec![component(1), component(2)]);
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ä"), b(param(1))),
+            E::WriteIO(b(call(id("ä"), vec![E::WriteIO(b(vector()))]))),
+        ]),
+        parse(&State::default(), r"ä ¤ \.
+@ << ä @ << . :")
+    );
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ä"), b(param(1))),
+            call(
+                id("ä"),
+                vec![E::Apply(b(scope(param(1))), vec![vector().into()])]
+            ),
+        ]),
+        parse(&State::default(), r"ä ¤ \.
+ä !{\.) . :")
+    );
+}
+
+#[test]
+fn parse_arities_are_local_to_scopes_and_bodies() {
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(param(0))),
+            scope(E::Definition(id("ö"), b(number(1)))),
+            call(id("ö"), vec![scope(number(1)), scope(number(2))]),
+        ]),
+        parse(&State::default(), r"ö ¤ \\
+{ö ¤ .)
+ö {.) {:)")
+    );
+    assert_eq!(
+        r(vec![
+            E::Definition(id("ö"), b(param(0))),
+            E::Definition(id("ä"), b(E::Definition(id("ö"), b(number(1))))),
+            call(id("ö"), vec![scope(number(1)), scope(number(2))]),
+        ]),
+        parse(&State::default(), r"ö ¤ \\
+ä ¤ ö ¤ .
+ö {.) {:)")
+    );
+}
+
 #[test]
 fn parse_compare_equality_of_number_and_function_call() {
     assert_eq!(
         r(vec![E::Op(
             b(number(24)),
             Op::Equ,
-            b(scope(E::Call(
-                Ident("ö".into()),
+            b(scope(call(
+                id("ö"),
                 vec![
                     number(1),
                     scope(number(3))
@@ -388,6 +668,7 @@ fn parse_condition_that_prints() {
     assert_eq!(
         r(vec![
             E::Conditional {
+                phrase: 0,
                 condition: b(E::Op(
                     b(number(2)),
                     Op::Equ,
@@ -396,7 +677,8 @@ fn parse_condition_that_prints() {
                 success: b(
                     E::WriteIO(b(E::Text("true".into())))
                 ),
-                failure: b(
+                else_phrase: 0,
+                failure: failure(
                     None
                 )
             }
@@ -410,16 +692,17 @@ fn parse_realer_expression() {
     assert_eq!(
         r(vec![
             E::Conditional {
+                phrase: 0,
                 condition: b(E::Op(
                     b(number(24)),
                     Op::Equ,
-                    b(scope(E::Call(
-                        Ident("ö".into()),
+                    b(scope(call(
+                        id("ö"),
                         vec![
                             // TODO: Is the parameter always a vector?
                             E::Vector(vec![
-                                VectorComponent::Number(1),
-                                VectorComponent::Number(3),
+                                component(1),
+                                component(3),
                             ])
                         ])
                     ))
@@ -427,7 +710,8 @@ fn parse_realer_expression() {
                 success: b(
                     E::WriteIO(b(E::Text("true".into())))
                 ),
-                failure: b(
+                else_phrase: 0,
+                failure: failure(
                     Some(E::WriteIO(b(E::Text("false".into()))))
                 )
             }
@@ -440,34 +724,38 @@ fn parse_realer_expression() {
 fn parse_example() {
     assert_eq!(
         r(vec![
-            E::Definition(Ident("ö".into()), b(
+            E::Definition(id("ö"), b(
                 E::Op(
-                    b(scope(E::Op(
-                        b(param(1)),
-                        Op::Add,
-                        b(param(2))
-                    ))), 
-                    Op::Mul,
-                    b(E::Op(b(param(1)), Op::Add, b(number(7))))
+                    b(E::Op(
+                        b(scope(E::Op(
+                            b(param(1)),
+                            Op::Add,
+                            b(param(2))
+                        ))),
+                        Op::Mul,
+                        b(param(1))
+                    )),
+                    Op::Add,
+                    b(number(7))
             ))),
             E::Conditional {
+                phrase: 0,
                 condition: b(E::Op(
                     b(number(24)),
                     Op::Equ,
-                    b(scope(E::Call(
-                        Ident("ö".into()),
+                    b(scope(call(
+                        id("ö"),
                         vec![
-                            E::Vector(vec![
-                                VectorComponent::Number(1),
-                                VectorComponent::Number(3),
-                            ])
+                            number(1),
+                            number(3),
                         ]
                     )))
                 )),
                 success: b(
                     E::WriteIO(b(E::Text("true".into())))
                 ),
-                failure: b(
+                else_phrase: 0,
+                failure: failure(
                     Some(E::WriteIO(b(E::Text("false".into()))))
                 )
             }
@@ -478,4 +766,121 @@ fn parse_example() {
 given that :::::::::::: = {ö . .:) @ << /true otherwise @ << /false
         ")
     )
-}
\ No newline at end of file
+}
+
+#[test]
+fn parse_error_unclosed_scope() {
+    assert_eq!(
+        (Reason::Expected("closing `)` for scope"), 1, 4, "/x)".into()),
+        err("{. /x)")
+    )
+}
+
+#[test]
+fn parse_error_reused_conditional_phrase() {
+    assert_eq!(
+        (Reason::ReusedConditional("given that"), 2, 1, "given that".into()),
+        err("given that . /a\ngiven that . /b")
+    )
+}
+
+#[test]
+fn parse_error_reused_else_phrase() {
+    assert_eq!(
+        (Reason::ReusedElse("otherwise"), 1, 42, "otherwise".into()),
+        err("given that . /a otherwise {whenever . /b otherwise .")
+    )
+}
+
+#[test]
+fn parse_conditional_phrases_are_reusable_after_all_are_used() {
+    let state = State::default();
+    for phrase in sos::parser::IFS {
+        assert!(parse(&state, &format!("{} . /a", phrase)).is_ok());
+    }
+    assert!(parse(&state, "given that . /a").is_ok());
+}
+
+#[test]
+fn parse_error_missing_operand() {
+    assert_eq!(
+        (Reason::Expected("expression"), 2, 5, ")".into()),
+        err(".\n. + )")
+    )
+}
+
+#[test]
+fn parse_error_empty_text() {
+    assert_eq!(
+        (Reason::Expected("text after `/`"), 1, 7, "".into()),
+        err("@ << /")
+    )
+}
+
+#[test]
+fn parse_error_rendering() {
+    let e = parse(&State::default(), ".\n{. /x)").expect_err("Parsing succeeded");
+    assert_eq!(
+        "error: expected closing `)` for scope
+ --> test.🆘:2:4
+  |
+2 | {. /x)
+  |    ^^^",
+        e.render("test.🆘")
+    )
+}
+
+#[test]
+fn parse_error_rendering_of_reused_phrase() {
+    let e = parse(&State::default(), "given that . /a\ngiven that . /b")
+        .expect_err("Parsing succeeded");
+    assert_eq!(
+        "error: reused conditional phrase `given that`
+ --> test.🆘:2:1
+  |
+2 | given that . /b
+  | ^^^^^^^^^^",
+        e.render("test.🆘")
+    )
+}
+
+#[test]
+fn parse_error_offset_at_end_of_incomplete_input() {
+    let code = "ö ¤ . + ";
+    let e = parse(&State::default(), code).expect_err("Parsing succeeded");
+    assert_eq!(Reason::Expected("expression"), e.reason);
+    assert_eq!(code.len(), e.offset);
+}
+
+#[test]
+fn parse_spans() {
+    let ast = parse(&State::default(), ". :\n  ö ¤ {\\. + \\:)").expect("Parsing failed");
+    let span = |s: &Span| (s.start, s.end, s.line, s.column);
+    assert_eq!((0, 3, 1, 1), span(&ast[0].span));
+    match &ast[0].kind {
+        E::Vector(v) => match &v[1] {
+            VectorComponent::Number(_, s) => assert_eq!((2, 3, 1, 3), span(s)),
+            c => panic!("Unexpected component {:?}", c),
+        },
+        e => panic!("Unexpected expression {:?}", e),
+    }
+    assert_eq!((6, 21, 2, 3), span(&ast[1].span));
+    match &ast[1].kind {
+        E::Definition(name, body) => {
+            assert_eq!((6, 8, 2, 3), span(&name.1));
+            assert_eq!((12, 21, 2, 7), span(&body.span));
+            match &body.kind {
+                E::Scope(op) => match &op.kind {
+                    E::Op(lhs, Op::Add, rhs) => {
+                        assert_eq!((13, 20, 2, 8), span(&op.span));
+                        assert_eq!((13, 15, 2, 8), span(&lhs.span));
+                        assert_eq!((18, 20, 2, 13), span(&rhs.span));
+                    }
+                    e => panic!("Unexpected expression {:?}", e),
+                },
+                e => panic!("Unexpected expression {:?}", e),
+            }
+        }
+        e => panic!("Unexpected expression {:?}", e),
+    }
+}
@@ -0,0 +1,118 @@
+use sos::parser::{parse, Expr, State, ELSES, IFS};
+use sos::printer::{print, print_number, print_text};
+
+fn round_trip(code: &str) -> Vec<Expr> {
+    let ast = parse(&State::default(), code).expect("Parsing failed");
+    let printed = print(&ast);
+    assert_eq!(
+        Ok(ast.clone()),
+        parse(&State::default(), &printed),
+        "`{}` was printed as `{}`",
+        code,
+        printed
+    );
+    ast
+}
+
+#[test]
+fn print_numbers() {
+    assert_eq!(".", print_number(1));
+    assert_eq!(":", print_number(2));
+    assert_eq!(".:", print_number(3));
+    assert_eq!("::", print_number(4));
+    assert_eq!(".:::", print_number(7));
+    assert_eq!("::::::::::::", print_number(24));
+}
+
+#[test]
+fn print_texts() {
+    assert_eq!("/simple", print_text("simple"));
+    assert_eq!("/Hello,/ World!", print_text("Hello, World!"));
+    assert_eq!("/ / ", print_text("  "));
+    assert_eq!("//", print_text("/"));
+    assert_eq!("// ", print_text("/ "));
+    assert_eq!("/ //", print_text(" /"));
+    assert_eq!("/ey// ey", print_text("ey/ ey"));
+}
+
+#[test]
+fn print_example() {
+    let code = "ö ¤ {\\. + \\:) * \\. + .:::
+given that :::::::::::: = {ö . .:) @ << /true otherwise @ << /false
+";
+    assert_eq!(code, print(&round_trip(code)));
+}
+
+#[test]
+fn print_canonical_layout() {
+    let ast = round_trip("   ö¤{ .+ \\\\ )\n\n\n{ö . { .:\n@>>#");
+    assert_eq!("ö ¤ {. + \\\\)\n{ö . {.:))\n@ >> #\n", print(&ast));
+}
+
+#[test]
+fn print_text_before_scope_end() {
+    let ast = round_trip("{ö /a   )");
+    assert_eq!("{ö /a )\n", print(&ast));
+}
+
+#[test]
+fn print_text_ending_in_slash_before_scope_end() {
+    let ast = round_trip("{{//");
+    assert_eq!("{{//\n", print(&ast));
+    assert_eq!("{{//\n", print(&round_trip(&print(&ast))));
+    let ast = round_trip("@ << {/a//\n/b");
+    assert_eq!("@ << {/a//\n/b\n", print(&ast));
+}
+
+#[test]
+fn print_keeps_conditional_phrases() {
+    let code = "whenever . /a otherwise /b\nassuming that : /c variously /d\n";
+    assert_eq!(code, print(&round_trip(code)));
+    let code = IFS
+        .iter()
+        .rev()
+        .chain(IFS)
+        .zip(ELSES.iter().chain(ELSES))
+        .map(|(phrase, otherwise)| format!("{} . /a {} /b\n", phrase, otherwise))
+        .collect::<String>();
+    assert_eq!(code, print(&round_trip(&code)));
+}
+
+#[test]
+fn print_round_trips() {
+    for code in &[
+        ".",
+        ". : .: ::",
+        "given that.{.",
+        "given that . { . ) otherwise . ",
+        "öäå¤.",
+        "{ ö ¤ . )",
+        "{ö¤.)={ö¤.)",
+        r"ö ¤ \.
+        ä ¤ \.",
+        r"ö ¤ \\",
+        "{.",
+        ". + . - : * .: = ::",
+        ". * {: - .:) - ::",
+        "@ << . + .",
+        "@ << @ >> # + @ >>> + @ >>",
+        "/ / ",
+        "// ",
+        "/ //",
+        "/ey// ey",
+        "/Hello,/ World!",
+        "@ << /true ",
+        "{ö { . ) .:)",
+        ":::::::::::: = {ö . { .:",
+        r"given that : = . @ << /true",
+        r"given that :::::::::::: = {ö . .:) @ << /true otherwise @ << /false",
+        r"ö ¤ . \. : \\",
+        "whenever {given that . /a otherwise /b ) {ö /c ) or else assuming that : /d",
+        r"¤ \. + :",
+        r"ö {¤ \.) . = ! {¤ /a ) . :",
+        r"ö ¤ ! \. {¤ \:) :",
+        "🆘 ¤ .\n🆘",
+    ] {
+        round_trip(code);
+    }
+}
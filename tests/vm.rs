@@ -0,0 +1,82 @@
+use sos::parser::{self, Op};
+use sos::vm::{compile, Instr};
+
+fn compiled(code: &str) -> Vec<Instr> {
+    let ast = parser::parse(&parser::State::default(), code).expect("Parsing failed");
+    compile(&ast).instrs
+}
+
+#[test]
+fn compile_operation() {
+    let instrs = compiled(r"\. + :");
+    assert!(matches!(
+        instrs.as_slice(),
+        [
+            Instr::Param(1, 1),
+            Instr::Numbers(rhs, _),
+            Instr::Force(1),
+            Instr::Op(Op::Add, 0),
+        ] if rhs == &[2]
+    ));
+}
+
+#[test]
+fn compile_operation_without_forcing_values() {
+    let instrs = compiled(". + :");
+    assert!(matches!(
+        instrs.as_slice(),
+        [
+            Instr::Numbers(lhs, _),
+            Instr::Numbers(rhs, _),
+            Instr::Op(Op::Add, 0),
+        ] if lhs == &[1] && rhs == &[2]
+    ));
+}
+
+#[test]
+fn compile_equality_without_forcing() {
+    let instrs = compiled(". = :");
+    assert!(matches!(
+        instrs.as_slice(),
+        [
+            Instr::Numbers(_, _),
+            Instr::Numbers(_, _),
+            Instr::Op(Op::Equ, 0)
+        ]
+    ));
+}
+
+#[test]
+fn compile_conditional() {
+    let instrs = compiled(r"given that \. /a");
+    assert!(matches!(
+        instrs.as_slice(),
+        [
+            Instr::Param(1, 1),
+            Instr::Force(0),
+            Instr::JumpUnless(5),
+            Instr::Text(_, _),
+            Instr::Jump(6),
+            Instr::Empty,
+        ]
+    ));
+}
+
+#[test]
+fn compile_all_params() {
+    let instrs = compiled(r"\\");
+    assert!(matches!(
+        instrs.as_slice(),
+        [Instr::Args(0), Instr::ForceArgs, Instr::ConcatArgs(0)]
+    ));
+}
+
+#[test]
+fn compile_program() {
+    let instrs = compiled("/a\n/b");
+    assert!(matches!(
+        instrs.as_slice(),
+        [Instr::Text(a, _), Instr::Pop, Instr::Text(b, _)] if a == "a" && b == "b"
+    ));
+    assert!(matches!(compiled("").as_slice(), [Instr::Empty]));
+}